@@ -59,7 +59,7 @@ pub extern "C" fn jsonnet_string_output(vm: &State, v: c_int) {
 	match v {
 		1 => vm.set_manifest_format(ManifestFormat::String),
 		0 => vm.set_manifest_format(ManifestFormat::Json {
-			padding: 4,
+			padding: "    ".to_owned(),
 			#[cfg(feature = "exp-preserve-order")]
 			preserve_order: false,
 		}),
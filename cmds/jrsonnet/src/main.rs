@@ -1,7 +1,9 @@
+mod repl;
+
 use std::{
 	env::current_dir,
 	fs::{create_dir_all, File},
-	io::{Read, Write},
+	io::{IsTerminal, Read, Write},
 };
 
 use clap::{AppSettings, IntoApp, Parser};
@@ -20,6 +22,14 @@ enum SubOpts {
 		/// Target shell name
 		shell: Shell,
 	},
+	/// Evaluate expressions read from stdin line by line,
+	/// keeping `local` definitions between them
+	Repl {
+		#[clap(flatten)]
+		general: GeneralOpts,
+		#[clap(flatten)]
+		manifest: ManifestOpts,
+	},
 }
 
 #[derive(Parser)]
@@ -77,6 +87,14 @@ fn main() {
 				generate(shell, app, "jrsonnet", buf);
 				std::process::exit(0)
 			}
+			SubOpts::Repl { general, manifest } => {
+				let s = State::default();
+				if let Err(e) = repl_real(&s, &general, &manifest) {
+					print_error(&s, e);
+					std::process::exit(1)
+				}
+				std::process::exit(0)
+			}
 		}
 	}
 
@@ -117,16 +135,20 @@ fn main_catch(opts: Opts) -> bool {
 	let _printer = opts.gc.stats_printer();
 	let s = State::default();
 	if let Err(e) = main_real(&s, opts) {
-		if let Error::Evaluation(e) = e {
-			eprintln!("{}", s.stringify_err(&e));
-		} else {
-			eprintln!("{}", e);
-		}
+		print_error(&s, e);
 		return false;
 	}
 	true
 }
 
+fn print_error(s: &State, e: Error) {
+	if let Error::Evaluation(e) = e {
+		eprintln!("{}", s.stringify_err(&e));
+	} else {
+		eprintln!("{}", e);
+	}
+}
+
 fn main_real(s: &State, opts: Opts) -> Result<(), Error> {
 	opts.general.configure(s)?;
 	opts.manifest.configure(s)?;
@@ -172,6 +194,9 @@ fn main_real(s: &State, opts: Opts) -> Result<(), Error> {
 		let mut file = File::create(path)?;
 		writeln!(file, "{}", s.manifest(val)?)?;
 	} else {
+		if std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none() {
+			s.set_highlight_output(true);
+		}
 		let output = s.manifest(val)?;
 		if !output.is_empty() {
 			println!("{}", output);
@@ -180,3 +205,10 @@ fn main_real(s: &State, opts: Opts) -> Result<(), Error> {
 
 	Ok(())
 }
+
+fn repl_real(s: &State, general: &GeneralOpts, manifest: &ManifestOpts) -> Result<(), Error> {
+	general.configure(s)?;
+	manifest.configure(s)?;
+	repl::run(s)?;
+	Ok(())
+}
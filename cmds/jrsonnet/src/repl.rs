@@ -0,0 +1,237 @@
+use std::io::{self, BufRead, Write};
+
+use jrsonnet_evaluator::{
+	destructure::evaluate_dest,
+	error::{Error, LocError, Result},
+	evaluate,
+	gc::GcHashMap,
+	Context, State,
+};
+use jrsonnet_parser::{Expr, LocExpr};
+
+/// Parse error is located at the end of input, so more lines may complete it
+fn is_incomplete(e: &LocError, code: &str) -> bool {
+	matches!(
+		e.error(),
+		Error::ImportSyntaxError { error, .. } if error.location.offset >= code.trim_end().len()
+	)
+}
+
+/// Extends context with bindings of `local a = ...; local b = ...; null` expression
+fn bind(mut ctx: Context, mut expr: &LocExpr) -> Result<Context> {
+	while let Expr::LocalExpr(binds, returned) = &*expr.0 {
+		let mut new_bindings = GcHashMap::with_capacity(binds.len());
+		let fctx = Context::new_future();
+		for b in binds {
+			evaluate_dest(b, fctx.clone(), &mut new_bindings)?;
+		}
+		ctx = ctx.extend(new_bindings, None, None, None).into_future(fctx);
+		expr = returned;
+	}
+	Ok(ctx)
+}
+
+/// Outcome of a single input line
+#[derive(Debug, PartialEq, Eq)]
+pub enum Response {
+	/// Input doesn't parse yet, more lines are expected
+	Incomplete,
+	/// Input is handled, and there is nothing to print
+	Empty,
+	/// Manifested value, or list of variables, should be printed to stdout
+	Output(String),
+	/// Formatted error, should be printed to stderr
+	Error(String),
+	Quit,
+}
+
+/// Line handling of the repl, independent from terminal io
+pub struct Repl {
+	s: State,
+	ctx: Context,
+	buf: String,
+	counter: usize,
+}
+
+impl Repl {
+	pub fn new(s: State) -> Self {
+		let ctx = s.create_default_context();
+		Self {
+			s,
+			ctx,
+			buf: String::new(),
+			counter: 0,
+		}
+	}
+
+	pub fn prompt(&self) -> &'static str {
+		if self.buf.is_empty() {
+			"> "
+		} else {
+			"... "
+		}
+	}
+
+	/// Input ending with `;` and starting with `local` defines variables, which stay
+	/// available for subsequent inputs. Input is continued on the next line until it parses.
+	/// `:vars` lists visible variables, `:quit` exits
+	pub fn feed(&mut self, line: &str) -> Response {
+		if self.buf.is_empty() {
+			match line.trim() {
+				"" => return Response::Empty,
+				":quit" | ":q" => return Response::Quit,
+				":vars" => {
+					let names: Vec<_> = self
+						.ctx
+						.visible_bindings()
+						.iter()
+						.map(ToString::to_string)
+						.collect();
+					return Response::Output(names.join("\n"));
+				}
+				_ => {}
+			}
+		}
+		self.buf.push_str(line);
+		self.buf.push('\n');
+
+		let is_binding = {
+			let trimmed = self.buf.trim();
+			trimmed.starts_with("local") && trimmed.ends_with(';')
+		};
+		let code = if is_binding {
+			format!("{}null", self.buf)
+		} else {
+			self.buf.clone()
+		};
+		let parsed = match self
+			.s
+			.parse_snippet(format!("<repl:{}>", self.counter + 1), code.clone())
+		{
+			Ok(parsed) => parsed,
+			Err(e) if is_incomplete(&e, &code) => return Response::Incomplete,
+			Err(e) => {
+				self.buf.clear();
+				return Response::Error(self.s.stringify_err(&e));
+			}
+		};
+		self.buf.clear();
+		self.counter += 1;
+
+		if is_binding {
+			match bind(self.ctx.clone(), &parsed) {
+				Ok(new_ctx) => {
+					self.ctx = new_ctx;
+					Response::Empty
+				}
+				Err(e) => Response::Error(self.s.stringify_err(&e)),
+			}
+		} else {
+			match evaluate(self.s.clone(), self.ctx.clone(), &parsed)
+				.and_then(|v| self.s.manifest(v))
+			{
+				Ok(out) => Response::Output(out.to_string()),
+				Err(e) => Response::Error(self.s.stringify_err(&e)),
+			}
+		}
+	}
+}
+
+/// Reads expressions from stdin, printing their manifested values.
+///
+/// See [`Repl::feed`] for accepted input
+pub fn run(s: &State) -> io::Result<()> {
+	let mut repl = Repl::new(s.clone());
+	let stdin = io::stdin();
+	let mut lines = stdin.lock().lines();
+	loop {
+		print!("{}", repl.prompt());
+		io::stdout().flush()?;
+		let line = match lines.next() {
+			Some(line) => line?,
+			None => break,
+		};
+		match repl.feed(&line) {
+			Response::Incomplete | Response::Empty => {}
+			Response::Output(out) => println!("{}", out),
+			Response::Error(e) => eprintln!("{}", e),
+			Response::Quit => break,
+		}
+	}
+	println!();
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use jrsonnet_evaluator::{ManifestFormat, State};
+
+	use super::{Repl, Response};
+
+	fn repl() -> Repl {
+		let s = State::default();
+		s.with_stdlib();
+		s.set_manifest_format(ManifestFormat::ToString);
+		Repl::new(s)
+	}
+
+	fn output(v: &str) -> Response {
+		Response::Output(v.to_owned())
+	}
+
+	#[test]
+	fn expression() {
+		let mut r = repl();
+		assert_eq!(r.feed("1 + 2"), output("3"));
+		assert_eq!(r.feed(""), Response::Empty);
+	}
+
+	#[test]
+	fn bindings_persist() {
+		let mut r = repl();
+		assert_eq!(r.feed("local a = 1;"), Response::Empty);
+		assert_eq!(r.feed("local b = a + 1, c = b * 2;"), Response::Empty);
+		assert_eq!(r.feed("[a, b, c]"), output("[1, 2, 4]"));
+		assert_eq!(r.feed("local a = 10;"), Response::Empty);
+		assert_eq!(r.feed("a + b"), output("12"));
+	}
+
+	#[test]
+	fn multiline() {
+		let mut r = repl();
+		assert_eq!(r.prompt(), "> ");
+		assert_eq!(r.feed("{"), Response::Incomplete);
+		assert_eq!(r.prompt(), "... ");
+		assert_eq!(r.feed("  a: 1,"), Response::Incomplete);
+		assert_eq!(r.feed("}"), output(r#"{"a": 1}"#));
+		assert_eq!(r.prompt(), "> ");
+	}
+
+	#[test]
+	fn errors_reset_input() {
+		let mut r = repl();
+		assert!(matches!(r.feed("1 +* 2"), Response::Error(_)));
+		assert_eq!(r.prompt(), "> ");
+		assert!(matches!(r.feed("error 'boom'"), Response::Error(e) if e.contains("boom")));
+		assert!(matches!(
+			r.feed("local a = error 'x'; a"),
+			Response::Error(_)
+		));
+		assert_eq!(r.feed("2"), output("2"));
+	}
+
+	#[test]
+	fn commands() {
+		let mut r = repl();
+		r.feed("local zzz = 1;");
+		match r.feed(":vars") {
+			Response::Output(vars) => {
+				assert!(vars.lines().any(|v| v == "zzz"));
+				assert!(vars.lines().any(|v| v == "std"));
+			}
+			other => panic!("unexpected response: {:?}", other),
+		}
+		assert_eq!(r.feed(":q"), Response::Quit);
+		assert_eq!(r.feed(":quit"), Response::Quit);
+	}
+}
@@ -38,6 +38,11 @@ pub struct MiscOpts {
 	#[clap(long)]
 	no_stdlib: bool,
 
+	/// Load standard library source from this file, instead of the built-in one.
+	/// Useful to try standard library changes without recompiling.
+	#[clap(long)]
+	stdlib_path: Option<PathBuf>,
+
 	/// Maximal allowed number of stack frames,
 	/// stack overflow error will be raised if this number gets exceeded.
 	#[clap(long, short = 's', default_value = "200")]
@@ -49,9 +54,24 @@ pub struct MiscOpts {
 	/// which should contain a colon-separated (semicolon-separated on Windows) list of directories.
 	#[clap(long, short = 'J', multiple_occurrences = true)]
 	jpath: Vec<PathBuf>,
+
+	/// Print warning when manifested integer is too big to be represented exactly,
+	/// which usually means some ID was silently mangled,
+	/// or when manifested number is not parsed back to the same value.
+	#[clap(long)]
+	warn_precision_loss: bool,
+
+	/// RFC3339 time to be returned by `std.dateNow()`.
+	/// Current time is never read implicitly, so output only depends on the passed value.
+	#[cfg(feature = "dates")]
+	#[clap(long)]
+	now: Option<String>,
 }
 impl ConfigureState for MiscOpts {
 	fn configure(&self, s: &State) -> Result<()> {
+		if let Some(path) = &self.stdlib_path {
+			s.set_stdlib_path(path)?;
+		}
 		if !self.no_stdlib {
 			s.with_stdlib();
 		}
@@ -65,6 +85,15 @@ impl ConfigureState for MiscOpts {
 		s.set_import_resolver(Box::new(FileImportResolver { library_paths }));
 
 		s.set_max_stack(self.max_stack);
+		s.set_warn_precision_loss(self.warn_precision_loss);
+		#[cfg(feature = "dates")]
+		if let Some(now) = &self.now {
+			use jrsonnet_evaluator::{chrono::DateTime, error::Error::RuntimeError};
+			let now = DateTime::parse_from_rfc3339(now).map_err(|e| {
+				RuntimeError(format!("invalid --now value {:?}: {}", now, e).into())
+			})?;
+			s.set_now(Some(now));
+		}
 		Ok(())
 	}
 }
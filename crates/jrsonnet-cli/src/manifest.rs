@@ -9,7 +9,18 @@ pub enum ManifestFormatName {
 	/// Expect string as output, and write them directly
 	String,
 	Json,
+	/// Json with `//` comments, written from `__comment_<key>__` fields
+	Jsonc,
+	/// Newline-delimited json, expects array as output
+	Jsonl,
+	#[cfg(feature = "yaml")]
 	Yaml,
+	/// Expects object as output
+	Toml,
+	/// Expects `JsonML` array as output
+	Xml,
+	/// Expects array of flat objects as output
+	Csv,
 }
 
 impl FromStr for ManifestFormatName {
@@ -18,31 +29,54 @@ impl FromStr for ManifestFormatName {
 		Ok(match s {
 			"string" => ManifestFormatName::String,
 			"json" => ManifestFormatName::Json,
+			"jsonc" => ManifestFormatName::Jsonc,
+			"jsonl" => ManifestFormatName::Jsonl,
+			#[cfg(feature = "yaml")]
 			"yaml" => ManifestFormatName::Yaml,
+			"toml" => ManifestFormatName::Toml,
+			"xml" => ManifestFormatName::Xml,
+			"csv" => ManifestFormatName::Csv,
 			_ => return Err("no such format"),
 		})
 	}
 }
 
+#[cfg(feature = "yaml")]
+const FORMATS: &[&str] = &[
+	"string", "json", "jsonc", "jsonl", "yaml", "toml", "xml", "csv",
+];
+#[cfg(not(feature = "yaml"))]
+const FORMATS: &[&str] = &["string", "json", "jsonc", "jsonl", "toml", "xml", "csv"];
+
 #[derive(Parser)]
 #[clap(next_help_heading = "MANIFESTIFICATION OUTPUT")]
 pub struct ManifestOpts {
 	/// Output format, wraps resulting value to corresponding std.manifest call.
 	/// If set to `string` then plain string value is expected to be returned,
 	/// otherwise output will be serialized to the specified format.
-	#[clap(long, short = 'f', default_value = "json", possible_values = &["string", "json", "yaml"])]
+	#[clap(long, short = 'f', default_value = "json", possible_values = FORMATS)]
 	format: ManifestFormatName,
 	/// Expect plain string as output.
 	/// Shortcut for `--format=string` thus this option is mutually exclusive with `format` option.
 	#[clap(long, short = 'S')]
 	string: bool,
-	/// Write output as YAML stream, can be used with --format json/yaml
+	/// Write output as YAML stream, can be used with --format json/yaml.
+	/// For newline-delimited json stream, use --format jsonl instead
+	#[cfg(feature = "yaml")]
 	#[clap(long, short = 'y')]
 	yaml_stream: bool,
 	/// Number of spaces to pad output manifest with.
-	/// `0` for hard tabs, `-1` for single line output [default: 3 for json, 2 for yaml]
+	/// `0` for hard tabs, `-1` for single line output [default: 3 for json and jsonc, 2 for yaml, toml and xml]
 	#[clap(long)]
 	line_padding: Option<usize>,
+	/// String to indent json and jsonc output with, i.e `$'\t'` for hard tabs.
+	/// Takes precedence over `line_padding` for json and jsonc output
+	#[clap(long)]
+	indent_string: Option<String>,
+	/// Comma-separated list of columns for csv output.
+	/// By default, fields of the first row are used
+	#[clap(long)]
+	csv_headers: Option<String>,
 	/// Preserve order in object manifestification
 	#[cfg(feature = "exp-preserve-order")]
 	#[clap(long)]
@@ -58,17 +92,55 @@ impl ConfigureState for ManifestOpts {
 			match self.format {
 				ManifestFormatName::String => s.set_manifest_format(ManifestFormat::String),
 				ManifestFormatName::Json => s.set_manifest_format(ManifestFormat::Json {
-					padding: self.line_padding.unwrap_or(3),
+					padding: self
+						.indent_string
+						.clone()
+						.unwrap_or_else(|| " ".repeat(self.line_padding.unwrap_or(3))),
+					#[cfg(feature = "exp-preserve-order")]
+					preserve_order,
+				}),
+				ManifestFormatName::Jsonc => s.set_manifest_format(ManifestFormat::Jsonc {
+					padding: self
+						.indent_string
+						.clone()
+						.unwrap_or_else(|| " ".repeat(self.line_padding.unwrap_or(3))),
 					#[cfg(feature = "exp-preserve-order")]
 					preserve_order,
 				}),
+				// Output is always terminated with newline by writer
+				ManifestFormatName::Jsonl => s.set_manifest_format(ManifestFormat::Jsonl {
+					trailing_newline: false,
+					#[cfg(feature = "exp-preserve-order")]
+					preserve_order,
+				}),
+				#[cfg(feature = "yaml")]
 				ManifestFormatName::Yaml => s.set_manifest_format(ManifestFormat::Yaml {
 					padding: self.line_padding.unwrap_or(2),
 					#[cfg(feature = "exp-preserve-order")]
 					preserve_order,
 				}),
+				ManifestFormatName::Toml => s.set_manifest_format(ManifestFormat::Toml {
+					padding: self.line_padding.unwrap_or(2),
+					#[cfg(feature = "exp-preserve-order")]
+					preserve_order,
+				}),
+				ManifestFormatName::Xml => s.set_manifest_format(ManifestFormat::Xml {
+					indent: " ".repeat(self.line_padding.unwrap_or(2)),
+					self_closing: true,
+					#[cfg(feature = "exp-preserve-order")]
+					preserve_order,
+				}),
+				ManifestFormatName::Csv => s.set_manifest_format(ManifestFormat::Csv {
+					headers: self
+						.csv_headers
+						.as_ref()
+						.map(|h| h.split(',').map(Into::into).collect()),
+					#[cfg(feature = "exp-preserve-order")]
+					preserve_order,
+				}),
 			}
 		}
+		#[cfg(feature = "yaml")]
 		if self.yaml_stream {
 			s.set_manifest_format(ManifestFormat::YamlStream(Box::new(s.manifest_format())))
 		}
@@ -3,7 +3,7 @@ use std::str::FromStr;
 use clap::Parser;
 use jrsonnet_evaluator::{
 	error::Result,
-	trace::{CompactFormat, ExplainingFormat, PathResolver},
+	trace::{CompactFormat, ExplainingFormat, FrameFilter, PathResolver},
 	State,
 };
 
@@ -35,10 +35,20 @@ pub struct TraceOpts {
 	/// thus being more verbose.
 	#[clap(long, possible_values = &["compact", "explaining"])]
 	trace_format: Option<TraceFormatName>,
+	/// Indentation of stack trace elements in `compact` format.
+	#[clap(long, default_value = "4")]
+	trace_padding: usize,
+	/// Separator between location and description of stack trace elements in `compact` format.
+	#[clap(long, default_value = " ")]
+	trace_separator: String,
 	/// Amount of stack trace elements to be displayed.
 	/// If set to `0` then full stack trace will be displayed.
 	#[clap(long, short = 't', default_value = "20")]
 	max_trace: usize,
+	/// Collapse stack trace elements located in the standard library,
+	/// full trace is displayed by default.
+	#[clap(long)]
+	hide_std_trace: bool,
 }
 impl ConfigureState for TraceOpts {
 	fn configure(&self, s: &State) -> Result<()> {
@@ -47,6 +57,11 @@ impl ConfigureState for TraceOpts {
 		} else {
 			PathResolver::Absolute
 		};
+		let filter = if self.hide_std_trace {
+			FrameFilter::Std
+		} else {
+			FrameFilter::None
+		};
 		match self
 			.trace_format
 			.as_ref()
@@ -54,10 +69,12 @@ impl ConfigureState for TraceOpts {
 		{
 			TraceFormatName::Compact => s.set_trace_format(Box::new(CompactFormat {
 				resolver,
-				padding: 4,
+				padding: self.trace_padding,
+				separator: self.trace_separator.clone(),
+				filter,
 			})),
 			TraceFormatName::Explaining => {
-				s.set_trace_format(Box::new(ExplainingFormat { resolver }))
+				s.set_trace_format(Box::new(ExplainingFormat { resolver, filter }))
 			}
 		}
 		s.set_max_trace(self.max_trace);
@@ -0,0 +1,109 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use jrsonnet_evaluator::State;
+
+/// Evaluates snippet built by `make_code` for every variant and input size
+fn bench_snippets(
+	c: &mut Criterion,
+	group: &str,
+	sizes: &[usize],
+	variants: &[(&str, &str)],
+	make_code: impl Fn(&str, usize) -> String,
+) {
+	let mut group = c.benchmark_group(group);
+	for &size in sizes {
+		for &(name, variant) in variants {
+			let s = State::default();
+			s.with_stdlib();
+			let code = make_code(variant, size);
+			group.bench_with_input(BenchmarkId::new(name, size), &code, |b, code| {
+				b.iter(|| {
+					s.evaluate_snippet("bench".to_owned(), code.clone())
+						.expect("snippet is valid")
+				});
+			});
+		}
+	}
+	group.finish();
+}
+
+/// Chained `+` of arrays, with accumulator being referenced by `std.foldl` on every step,
+/// followed by indexing of every element of the result.
+///
+/// Should grow linearly with part count, for both small parts (copied on concatenation)
+/// and big ones (referenced as parts of concatenation)
+fn concat(c: &mut Criterion) {
+	bench_snippets(
+		c,
+		"concat",
+		&[1_000, 10_000],
+		&[("small_parts", "1"), ("big_parts", "100")],
+		|part_len, count| {
+			format!(
+				"local parts = [std.range(1, {part_len}) for i in std.range(1, {count})];
+				local arr = std.foldl(function(acc, part) acc + part, parts, []);
+				std.foldl(function(acc, i) acc + arr[i], std.range(0, std.length(arr) - 1), 0)"
+			)
+		},
+	);
+}
+
+/// Loop over every char of the string, which was quadratic when indexing was O(n)
+fn string_index(c: &mut Criterion) {
+	bench_snippets(
+		c,
+		"string_index",
+		&[1_000, 10_000],
+		&[("ascii", "a"), ("unicode", "ы")],
+		|chr, len| {
+			format!(
+				"local s = std.repeat('{chr}', {len});
+				std.foldl(function(acc, i) acc + std.codepoint(s[i]), std.range(0, std.length(s) - 1), 0)"
+			)
+		},
+	);
+}
+
+/// Native `std.set` deduplicates string keys by hash before sorting,
+/// compared with sorting everything and dropping adjacent duplicates
+fn set(c: &mut Criterion) {
+	bench_snippets(
+		c,
+		"set",
+		&[1_000, 10_000],
+		&[
+			("native", "std.set(arr)"),
+			("sort_uniq", "std.uniq(std.sort(arr))"),
+		],
+		// Every value is repeated 10 times
+		|set, len| {
+			format!(
+				"local arr = [std.toString(i % {}) for i in std.range(0, {})]; std.length({set})",
+				len / 10,
+				len - 1,
+			)
+		},
+	);
+}
+
+/// Manifested json passed to binary consumer, either directly as bytes, or through utf8 encoding
+/// of manifested string
+fn manifest_bytes(c: &mut Criterion) {
+	bench_snippets(
+		c,
+		"manifest_bytes",
+		&[1_000, 10_000],
+		&[
+			("bytes", "std.manifestJsonBytes(v)"),
+			("string", "std.encodeUTF8(std.manifestJson(v))"),
+		],
+		|manifest, len| {
+			format!(
+				"local v = [{{ id: i, name: 'item ' + i }} for i in std.range(1, {len})];
+				std.length(std.base64({manifest}))"
+			)
+		},
+	);
+}
+
+criterion_group!(benches, concat, string_index, set, manifest_bytes);
+criterion_main!(benches);
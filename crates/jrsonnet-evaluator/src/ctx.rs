@@ -86,6 +86,16 @@ impl Context {
 	pub fn contains_binding(&self, name: IStr) -> bool {
 		self.0.bindings.contains_key(&name)
 	}
+	/// Names of all variables accessible in this context, sorted and deduplicated,
+	/// i.e. for autocompletion.
+	/// Bound values are not evaluated
+	pub fn visible_bindings(&self) -> Vec<IStr> {
+		let mut out = Vec::new();
+		self.0.bindings.clone().iter_keys(|k| out.push(k));
+		out.sort_unstable();
+		out.dedup();
+		out
+	}
 	#[must_use]
 	pub fn into_future(self, ctx: Pending<Self>) -> Self {
 		{
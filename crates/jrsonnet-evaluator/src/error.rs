@@ -7,8 +7,9 @@ use jrsonnet_types::ValType;
 use thiserror::Error;
 
 use crate::{
-	stdlib::{format::FormatError, sort::SortError},
+	stdlib::{format::FormatError, manifest::escape_string_json, sort::SortError},
 	typed::TypeLocError,
+	Val,
 };
 
 fn format_found(list: &[IStr], what: &str) -> String {
@@ -64,9 +65,9 @@ pub enum Error {
 	InComprehensionCanOnlyIterateOverArray,
 
 	#[error("array out of bounds: {0} is not within [0,{1})")]
-	ArrayBoundsError(usize, usize),
+	ArrayBoundsError(isize, usize),
 	#[error("string out of bounds: {0} is not within [0,{1})")]
-	StringBoundsError(usize, usize),
+	StringBoundsError(isize, usize),
 
 	#[error("assert failed: {}", format_empty_str(.0))]
 	AssertionFailed(IStr),
@@ -99,6 +100,8 @@ pub enum Error {
 	FieldMustBeStringGot(ValType),
 	#[error("duplicate field name: {}", format_empty_str(.0))]
 	DuplicateFieldName(IStr),
+	#[error("invalid field name: {}", escape_string_json(.0))]
+	InvalidFieldName(IStr),
 
 	#[error("attempted to index array with string {}", format_empty_str(.0))]
 	AttemptedIndexAnArrayWithString(IStr),
@@ -139,10 +142,18 @@ pub enum Error {
 
 	#[error("runtime error: {}", format_empty_str(.0))]
 	RuntimeError(IStr),
+	/// Thrown by `error` expression with non-string argument,
+	/// first field is a string fallback, second is the original value
+	#[error("runtime error: {}", format_empty_str(.0))]
+	StructuredRuntimeError(IStr, Val),
 	#[error("stack overflow, try to reduce recursion, or set --max-stack to bigger value")]
 	StackOverflow,
 	#[error("infinite recursion detected")]
 	InfiniteRecursionDetected,
+	#[error("tried to materialize cyclic value")]
+	MaterializeCycle,
+	#[error("function {0} not available in minimal stdlib")]
+	StdlibFunctionUnavailable(IStr),
 	#[error("tried to index by fractional value")]
 	FractionalIndex,
 	#[error("attempted to divide by zero")]
@@ -159,6 +170,8 @@ pub enum Error {
 	StreamManifestOutputCannotBeRecursed,
 	#[error("stream manifest output cannot consist of raw strings")]
 	StreamManifestCannotNestString,
+	#[error("tried to manifest array, which contains itself")]
+	InfiniteManifestRecursion,
 
 	#[error("{}", format_empty_str(.0))]
 	ImportCallbackError(String),
@@ -189,6 +202,16 @@ impl From<anyhow::Error> for LocError {
 	}
 }
 
+impl Error {
+	/// Value passed to `error` expression, if it wasn't a string
+	pub const fn payload(&self) -> Option<&Val> {
+		match self {
+			Self::StructuredRuntimeError(_, payload) => Some(payload),
+			_ => None,
+		}
+	}
+}
+
 impl From<Error> for LocError {
 	fn from(e: Error) -> Self {
 		Self::new(e)
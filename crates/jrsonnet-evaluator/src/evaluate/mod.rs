@@ -3,20 +3,22 @@ use std::{cmp::Ordering, rc::Rc};
 use jrsonnet_gcmodule::{Cc, Trace};
 use jrsonnet_interner::IStr;
 use jrsonnet_parser::{
-	ArgsDesc, AssertStmt, BindSpec, CompSpec, Expr, FieldMember, FieldName, ForSpecData,
-	IfSpecData, LiteralType, LocExpr, Member, ObjBody, ParamsDesc,
+	ArgsDesc, AssertStmt, BinaryOpType, BindSpec, CompSpec, Expr, FieldMember, FieldName,
+	ForSpecData, IfSpecData, LiteralType, LocExpr, Member, ObjBody, ParamsDesc,
 };
 use jrsonnet_types::ValType;
 
 use crate::{
 	destructure::evaluate_dest,
 	error::Error::*,
-	evaluate::operator::{evaluate_add_op, evaluate_binary_op_special, evaluate_unary_op},
+	evaluate::operator::{
+		evaluate_add_op, evaluate_binary_op_normal, evaluate_binary_op_special, evaluate_unary_op,
+	},
 	function::{CallLocation, FuncDesc, FuncVal},
-	stdlib::{std_slice, BUILTINS},
+	stdlib::{manifest::escape_string_json, std_slice, BUILTINS},
 	tb, throw,
 	typed::Typed,
-	val::{ArrValue, CachedUnbound, Thunk, ThunkValue},
+	val::{whole_number, ArrValue, CachedUnbound, Thunk, ThunkValue},
 	Context, GcHashMap, ObjValue, ObjValueBuilder, ObjectAssertion, Pending, Result, State,
 	Unbound, Val,
 };
@@ -374,13 +376,96 @@ pub fn evaluate_apply(
 	})
 }
 
+/// Operand values longer than that are truncated in assertion failure description
+const MAX_OPERAND_DESCRIPTION_LEN: usize = 64;
+
+/// Splits bare assertion condition `a == b` (or other comparison) into its operands,
+/// so they are evaluated once, and then reused to describe failed assertion
+fn comparison_operands(cond: &LocExpr) -> Option<(&LocExpr, &LocExpr, BinaryOpType, &LocExpr)> {
+	let mut cond = cond;
+	while let Expr::Parened(inner) = &*cond.0 {
+		cond = inner;
+	}
+	match &*cond.0 {
+		Expr::BinaryOp(
+			a,
+			op @ (BinaryOpType::Eq
+			| BinaryOpType::Neq
+			| BinaryOpType::Lt
+			| BinaryOpType::Gt
+			| BinaryOpType::Lte
+			| BinaryOpType::Gte),
+			b,
+		) => Some((cond, a, *op, b)),
+		_ => None,
+	}
+}
+
+/// Best-effort description of failed bare `assert a == b`, i.e `a == b: 1 != 2`
+fn describe_failed_comparison(
+	s: State,
+	cond: &LocExpr,
+	a: &Val,
+	op: BinaryOpType,
+	b: &Val,
+) -> Option<String> {
+	let negated = match op {
+		BinaryOpType::Eq => "!=",
+		BinaryOpType::Neq => "==",
+		BinaryOpType::Lt => ">=",
+		BinaryOpType::Gt => "<=",
+		BinaryOpType::Lte => ">",
+		BinaryOpType::Gte => "<",
+		_ => return None,
+	};
+	let describe = |value: &Val| -> Option<String> {
+		let out = match value {
+			Val::Str(v) => escape_string_json(v),
+			v => v.to_string(s.clone()).ok()?.to_string(),
+		};
+		Some(if out.chars().count() > MAX_OPERAND_DESCRIPTION_LEN {
+			let mut out: String = out.chars().take(MAX_OPERAND_DESCRIPTION_LEN).collect();
+			out.push_str("...");
+			out
+		} else {
+			out
+		})
+	};
+	let a_value = describe(a)?;
+	let b_value = describe(b)?;
+	let source = s
+		.get_source(cond.1 .0.clone())
+		.and_then(|code| {
+			code.get(cond.1 .1 as usize..cond.1 .2 as usize)
+				.map(ToOwned::to_owned)
+		})
+		.unwrap_or_else(|| format!("<lhs> {} <rhs>", op));
+	Some(format!("{}: {} {} {}", source, a_value, negated, b_value))
+}
+
 pub fn evaluate_assert(s: State, ctx: Context, assertion: &AssertStmt) -> Result<()> {
 	let value = &assertion.0;
 	let msg = &assertion.1;
-	let assertion_result = s.push(
+	let comparison = if msg.is_none() {
+		comparison_operands(value)
+	} else {
+		None
+	};
+	let (assertion_result, operands) = s.push(
 		CallLocation::new(&value.1),
 		|| "assertion condition".to_owned(),
-		|| bool::from_untyped(evaluate(s.clone(), ctx.clone(), value)?, s.clone()),
+		|| {
+			if let Some((_, a, op, b)) = comparison {
+				let a = evaluate(s.clone(), ctx.clone(), a)?;
+				let b = evaluate(s.clone(), ctx.clone(), b)?;
+				let result = evaluate_binary_op_normal(s.clone(), &a, op, &b)?;
+				return Ok((bool::from_untyped(result, s.clone())?, Some((a, b))));
+			}
+			Ok((
+				bool::from_untyped(evaluate(s.clone(), ctx.clone(), value)?, s.clone())?,
+				None,
+			))
+		},
 	)?;
 	if !assertion_result {
 		s.push(
@@ -392,6 +477,12 @@ pub fn evaluate_assert(s: State, ctx: Context, assertion: &AssertStmt) -> Result
 						evaluate(s.clone(), ctx, msg)?.to_string(s.clone())?
 					));
 				}
+				if let (Some((cond, _, op, _)), Some((a, b))) = (comparison, &operands) {
+					if let Some(description) = describe_failed_comparison(s.clone(), cond, a, op, b)
+					{
+						throw!(AssertionFailed(description.into()));
+					}
+				}
 				throw!(AssertionFailed(Val::Null.to_string(s.clone())?));
 			},
 		)?;
@@ -408,6 +499,19 @@ pub fn evaluate_named(s: State, ctx: Context, expr: &LocExpr, name: IStr) -> Res
 	})
 }
 
+/// Position of `index` in array of length `len`, with `ext-negative-index` feature
+/// negative indices are counted from the end, i.e `-1` is the last element
+fn array_position(
+	index: isize,
+	#[cfg_attr(not(feature = "ext-negative-index"), allow(unused_variables))] len: usize,
+) -> Option<usize> {
+	#[cfg(feature = "ext-negative-index")]
+	if index < 0 {
+		return len.checked_sub(index.unsigned_abs());
+	}
+	usize::try_from(index).ok()
+}
+
 #[allow(clippy::too_many_lines)]
 pub fn evaluate(s: State, ctx: Context, expr: &LocExpr) -> Result<Val> {
 	use Expr::*;
@@ -486,11 +590,15 @@ pub fn evaluate(s: State, ctx: Context, expr: &LocExpr) -> Result<Val> {
 				)),
 
 				(Val::Arr(v), Val::Num(n)) => {
-					if n.fract() > f64::EPSILON {
-						throw!(FractionalIndex)
-					}
-					v.get(s, n as usize)?
-						.ok_or_else(|| ArrayBoundsError(n as usize, v.len()))?
+					let index = match whole_number(n) {
+						Some(n) => n as isize,
+						None => throw!(FractionalIndex),
+					};
+					let item = match array_position(index, v.len()) {
+						Some(position) => v.get(s, position)?,
+						None => None,
+					};
+					item.ok_or_else(|| ArrayBoundsError(index, v.len()))?
 				}
 				(Val::Arr(_), Val::Str(n)) => throw!(AttemptedIndexAnArrayWithString(n)),
 				(Val::Arr(_), n) => throw!(ValueIndexMustBeTypeGot(
@@ -500,17 +608,14 @@ pub fn evaluate(s: State, ctx: Context, expr: &LocExpr) -> Result<Val> {
 				)),
 
 				(Val::Str(s), Val::Num(n)) => Val::Str({
-					let v: IStr = s
-						.chars()
-						.skip(n as usize)
-						.take(1)
-						.collect::<String>()
-						.into();
-					if v.is_empty() {
-						let size = s.chars().count();
-						throw!(StringBoundsError(n as usize, size))
+					let index = match whole_number(n) {
+						Some(n) => n as isize,
+						None => throw!(FractionalIndex),
+					};
+					match usize::try_from(index).ok().and_then(|i| s.char_at(i)) {
+						Some(c) => IStr::from(&*c.encode_utf8(&mut [0; 4])),
+						None => throw!(StringBoundsError(index, s.char_len())),
 					}
-					v
 				}),
 				(Val::Str(_), n) => throw!(ValueIndexMustBeTypeGot(
 					ValType::Str,
@@ -587,10 +692,9 @@ pub fn evaluate(s: State, ctx: Context, expr: &LocExpr) -> Result<Val> {
 		ErrorStmt(e) => s.push(
 			CallLocation::new(loc),
 			|| "error statement".to_owned(),
-			|| {
-				throw!(RuntimeError(
-					evaluate(s.clone(), ctx, e)?.to_string(s.clone())?,
-				))
+			|| match evaluate(s.clone(), ctx, e)? {
+				Val::Str(v) => throw!(RuntimeError(v)),
+				v => throw!(StructuredRuntimeError(v.to_string(s.clone())?, v)),
 			},
 		)?,
 		IfElse {
@@ -3,8 +3,13 @@ use std::cmp::Ordering;
 use jrsonnet_parser::{BinaryOpType, LocExpr, UnaryOpType};
 
 use crate::{
-	error::Error::*, evaluate, stdlib::std_format, throw, typed::Typed, val::equals, Context,
-	Result, State, Val,
+	error::Error::*,
+	evaluate,
+	stdlib::{manifest::format_number, std_format},
+	throw,
+	typed::Typed,
+	val::{equals, ArrValue},
+	Context, Result, State, Val,
 };
 
 pub fn evaluate_unary_op(op: UnaryOpType, b: &Val) -> Result<Val> {
@@ -21,22 +26,27 @@ pub fn evaluate_unary_op(op: UnaryOpType, b: &Val) -> Result<Val> {
 pub fn evaluate_add_op(s: State, a: &Val, b: &Val) -> Result<Val> {
 	use Val::*;
 	Ok(match (a, b) {
-		(Str(v1), Str(v2)) => Str(((**v1).to_owned() + v2).into()),
+		(Str(v1), Str(v2)) => Str(v1.concat(v2)),
 
 		// Can't use generic json serialization way, because it depends on number to string concatenation (std.jsonnet:890)
-		(Num(a), Str(b)) => Str(format!("{a}{b}").into()),
-		(Str(a), Num(b)) => Str(format!("{a}{b}").into()),
+		// Numbers are written the same way as manifestification does
+		(Num(a), Str(b)) => {
+			let mut out = String::new();
+			format_number(&mut out, *a);
+			out.push_str(b);
+			Str(out.into())
+		}
+		(Str(a), Num(b)) => {
+			let mut out = a.to_string();
+			format_number(&mut out, *b);
+			Str(out.into())
+		}
 
 		(Str(a), o) => Str(format!("{}{}", a, o.clone().to_string(s)?).into()),
 		(o, Str(a)) => Str(format!("{}{}", o.clone().to_string(s)?, a).into()),
 
 		(Obj(v1), Obj(v2)) => Obj(v2.extend_from(v1.clone())),
-		(Arr(a), Arr(b)) => {
-			let mut out = Vec::with_capacity(a.len() + b.len());
-			out.extend(a.iter_lazy());
-			out.extend(b.iter_lazy());
-			Arr(out.into())
-		}
+		(Arr(a), Arr(b)) => Arr(ArrValue::extended(a.clone(), b.clone())),
 		(Num(v1), Num(v2)) => Val::new_checked_num(v1 + v2)?,
 		_ => throw!(BinaryOperatorDoesNotOperateOnValues(
 			BinaryOpType::Add,
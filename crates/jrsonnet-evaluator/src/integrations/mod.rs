@@ -1 +1,2 @@
 pub mod serde;
+pub mod stream;
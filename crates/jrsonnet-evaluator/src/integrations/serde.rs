@@ -43,11 +43,18 @@ impl Typed for Value {
 			Val::Bool(b) => Self::Bool(b),
 			Val::Null => Self::Null,
 			Val::Str(s) => Self::String((&s as &str).into()),
-			Val::Num(n) => Self::Number(if n.fract() <= f64::EPSILON {
-				(n as i64).into()
-			} else {
-				Number::from_f64(n).expect("jsonnet numbers can't be infinite or NaN")
-			}),
+			// Only whole numbers, which fit in i64 are written as integers, bigger ones would be saturated.
+			// Negative zero is kept as float, as integer zero has no sign
+			Val::Num(n) => Self::Number(
+				if n.fract() == 0.0
+					&& n.abs() < i64::MAX as f64
+					&& !(n == 0.0 && n.is_sign_negative())
+				{
+					(n as i64).into()
+				} else {
+					Number::from_f64(n).expect("jsonnet numbers can't be infinite or NaN")
+				},
+			),
 			Val::Arr(a) => {
 				let mut out = Vec::with_capacity(a.len());
 				for item in a.iter(s.clone()) {
@@ -0,0 +1,166 @@
+//! Event-based parsing of JSON/YAML documents, for inputs too large to be materialized as [`Val`](crate::Val)
+//!
+//! Instead of building value tree, parser reports every encountered token to the host callback,
+//! which is free to aggregate only the parts it needs.
+
+use std::{fmt, io::Read};
+
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+
+use crate::error::{Error::*, LocError, Result};
+
+/// Single token of parsed document
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParseEvent<'a> {
+	Null,
+	Bool(bool),
+	Num(f64),
+	Str(&'a str),
+	ArrayStart,
+	ArrayEnd,
+	ObjectStart,
+	/// Object field name, always followed by events of field value
+	Key(&'a str),
+	ObjectEnd,
+}
+
+struct EventSeed<'h, H> {
+	handler: &'h mut H,
+	error: &'h mut Option<LocError>,
+}
+
+impl<'h, H> EventSeed<'h, H>
+where
+	H: FnMut(ParseEvent<'_>) -> Result<()>,
+{
+	fn reborrow(&mut self) -> EventSeed<'_, H> {
+		EventSeed {
+			handler: &mut *self.handler,
+			error: &mut *self.error,
+		}
+	}
+
+	fn emit<E: de::Error>(&mut self, event: ParseEvent<'_>) -> Result<(), E> {
+		(self.handler)(event).map_err(|e| {
+			*self.error = Some(e);
+			E::custom("aborted by handler")
+		})
+	}
+}
+
+impl<'de, 'h, H> DeserializeSeed<'de> for EventSeed<'h, H>
+where
+	H: FnMut(ParseEvent<'_>) -> Result<()>,
+{
+	type Value = ();
+
+	fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+		deserializer.deserialize_any(self)
+	}
+}
+
+impl<'de, 'h, H> Visitor<'de> for EventSeed<'h, H>
+where
+	H: FnMut(ParseEvent<'_>) -> Result<()>,
+{
+	type Value = ();
+
+	fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "any value")
+	}
+
+	fn visit_unit<E: de::Error>(mut self) -> Result<(), E> {
+		self.emit(ParseEvent::Null)
+	}
+	fn visit_none<E: de::Error>(mut self) -> Result<(), E> {
+		self.emit(ParseEvent::Null)
+	}
+	fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+		deserializer.deserialize_any(self)
+	}
+	fn visit_bool<E: de::Error>(mut self, v: bool) -> Result<(), E> {
+		self.emit(ParseEvent::Bool(v))
+	}
+	fn visit_i64<E: de::Error>(mut self, v: i64) -> Result<(), E> {
+		self.emit(ParseEvent::Num(v as f64))
+	}
+	fn visit_u64<E: de::Error>(mut self, v: u64) -> Result<(), E> {
+		self.emit(ParseEvent::Num(v as f64))
+	}
+	fn visit_f64<E: de::Error>(mut self, v: f64) -> Result<(), E> {
+		self.emit(ParseEvent::Num(v))
+	}
+	fn visit_str<E: de::Error>(mut self, v: &str) -> Result<(), E> {
+		self.emit(ParseEvent::Str(v))
+	}
+
+	fn visit_seq<A: SeqAccess<'de>>(mut self, mut seq: A) -> Result<(), A::Error> {
+		self.emit(ParseEvent::ArrayStart)?;
+		while seq.next_element_seed(self.reborrow())?.is_some() {}
+		self.emit(ParseEvent::ArrayEnd)
+	}
+
+	fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<(), A::Error> {
+		self.emit(ParseEvent::ObjectStart)?;
+		while let Some(key) = map.next_key::<String>()? {
+			self.emit(ParseEvent::Key(&key))?;
+			map.next_value_seed(self.reborrow())?;
+		}
+		self.emit(ParseEvent::ObjectEnd)
+	}
+}
+
+fn finish(
+	result: Result<(), impl fmt::Display>,
+	handler_error: Option<LocError>,
+	format: &str,
+) -> Result<()> {
+	if let Some(e) = handler_error {
+		return Err(e);
+	}
+	result.map_err(|e| RuntimeError(format!("failed to parse {}: {}", format, e).into()).into())
+}
+
+/// Parses single JSON document from `reader`, passing every token to `handler`
+///
+/// Input is consumed incrementally, only the currently parsed string/key is held in memory.
+/// Error returned from `handler` aborts parsing and is returned as-is.
+pub fn stream_json(
+	reader: impl Read,
+	mut handler: impl FnMut(ParseEvent<'_>) -> Result<()>,
+) -> Result<()> {
+	let mut handler_error = None;
+	let mut deserializer = serde_json::Deserializer::from_reader(reader);
+	let result = EventSeed {
+		handler: &mut handler,
+		error: &mut handler_error,
+	}
+	.deserialize(&mut deserializer)
+	.and_then(|()| deserializer.end());
+	finish(result, handler_error, "json")
+}
+
+/// Parses YAML stream, passing every token to `handler`
+///
+/// Every document of the stream is reported as separate top-level value,
+/// with the same quirks as `std.parseYaml`.
+#[cfg(feature = "yaml")]
+pub fn stream_yaml(
+	input: &str,
+	mut handler: impl FnMut(ParseEvent<'_>) -> Result<()>,
+) -> Result<()> {
+	use serde_yaml_with_quirks::{Deserializer, DeserializingQuirks};
+
+	let mut handler_error = None;
+	for document in
+		Deserializer::from_str_with_quirks(input, DeserializingQuirks { old_octals: true })
+	{
+		let result = EventSeed {
+			handler: &mut handler,
+			error: &mut handler_error,
+		}
+		.deserialize(document);
+		finish(result, handler_error.take(), "yaml")?;
+	}
+	Ok(())
+}
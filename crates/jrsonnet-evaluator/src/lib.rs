@@ -34,7 +34,7 @@ mod evaluate;
 pub mod function;
 pub mod gc;
 mod import;
-mod integrations;
+pub mod integrations;
 mod map;
 mod obj;
 mod stdlib;
@@ -42,15 +42,21 @@ pub mod trace;
 pub mod typed;
 pub mod val;
 
+#[cfg(feature = "test-util")]
+pub mod test_util;
+
 use std::{
 	borrow::Cow,
-	cell::{Ref, RefCell, RefMut},
+	cell::{Cell, Ref, RefCell, RefMut},
 	collections::HashMap,
 	fmt::{self, Debug},
 	path::{Path, PathBuf},
 	rc::Rc,
+	time::{Duration, Instant},
 };
 
+#[cfg(feature = "dates")]
+pub use chrono;
 pub use ctx::*;
 pub use dynamic::*;
 use error::{Error::*, LocError, Result, StackTraceElement};
@@ -65,7 +71,9 @@ pub use jrsonnet_interner::IStr;
 pub use jrsonnet_parser as parser;
 use jrsonnet_parser::*;
 pub use obj::*;
+pub use stdlib::manifest;
 use trace::{location_to_offset, offset_to_location, CodeLocation, CompactFormat, TraceFormat};
+use val::ThunkValue;
 pub use val::{ManifestFormat, Thunk, Val};
 
 pub trait Unbound: Trace {
@@ -98,6 +106,27 @@ impl LazyBinding {
 	}
 }
 
+/// Spacing of objects and arrays, implicitly converted to string, i.e with `obj + ''`
+/// or `std.toString(obj)`, default matches other jsonnet implementations: `{"a": [1, 2], "b": { }}`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ToStringSpacing {
+	/// Separator between object field names and values
+	pub key_val_sep: IStr,
+	/// Written after commas between array elements and object fields
+	pub item_sep: IStr,
+	/// Written inside of empty arrays and objects
+	pub empty_padding: IStr,
+}
+impl Default for ToStringSpacing {
+	fn default() -> Self {
+		Self {
+			key_val_sep: ": ".into(),
+			item_sep: " ".into(),
+			empty_padding: " ".into(),
+		}
+	}
+}
+
 pub struct EvaluationSettings {
 	/// Limits recursion by limiting the number of stack frames
 	pub max_stack: usize,
@@ -117,6 +146,30 @@ pub struct EvaluationSettings {
 	pub manifest_format: ManifestFormat,
 	/// Used for bindings
 	pub trace_format: Box<dyn TraceFormat>,
+	/// Print warning when manifested integer exceeds 2^53, and may have lost precision,
+	/// or when manifested number is not parsed back to the same value
+	pub warn_precision_loss: bool,
+	/// Highlight json output of [`State::manifest`] with ANSI escape codes,
+	/// see [`manifest::AnsiStyler`]. Other formats and std functions are not affected
+	pub highlight_output: bool,
+	/// Used by [`Val::to_string`]
+	pub to_string_spacing: ToStringSpacing,
+	/// Name of global variable holding standard library
+	pub stdlib_name: IStr,
+	/// Standard library source to use instead of the embedded one, see [`State::set_stdlib_path`]
+	pub custom_stdlib: Option<LocExpr>,
+	/// Time returned by `std.dateNow()`, real clock is never read, so evaluation is reproducible
+	#[cfg(feature = "dates")]
+	pub now: Option<chrono::DateTime<chrono::FixedOffset>>,
+	/// Collect reference cycles of the current thread, when the last handle to this state is dropped,
+	/// and no other state is alive on this thread
+	///
+	/// Interning is not scoped per state: interned strings are shared by the thread, and are released
+	/// once nothing references them. Values created during evaluation may form reference cycles,
+	/// which keep them (and strings referenced by them) alive until the next cycle collection.
+	/// Collection scans values of every state on the thread, so it is skipped while any other state
+	/// is alive. Useful for long-lived hosts, creating one state per request
+	pub collect_cycles_on_drop: bool,
 }
 impl Default for EvaluationSettings {
 	fn default() -> Self {
@@ -129,14 +182,24 @@ impl Default for EvaluationSettings {
 			tla_vars: HashMap::default(),
 			import_resolver: Box::new(DummyImportResolver),
 			manifest_format: ManifestFormat::Json {
-				padding: 4,
+				padding: "    ".to_owned(),
 				#[cfg(feature = "exp-preserve-order")]
 				preserve_order: false,
 			},
 			trace_format: Box::new(CompactFormat {
 				padding: 4,
+				separator: " ".to_owned(),
 				resolver: trace::PathResolver::Absolute,
+				filter: trace::FrameFilter::None,
 			}),
+			warn_precision_loss: false,
+			highlight_output: false,
+			to_string_spacing: ToStringSpacing::default(),
+			stdlib_name: "std".into(),
+			custom_stdlib: None,
+			#[cfg(feature = "dates")]
+			now: None,
+			collect_cycles_on_drop: false,
 		}
 	}
 }
@@ -154,6 +217,9 @@ struct EvaluationData {
 	files: GcHashMap<PathBuf, FileData>,
 	/// Contains tla arguments and others, which aren't needed to be obtained by name
 	volatile_files: GcHashMap<String, String>,
+
+	/// If set - `std.trace` and warnings are written here instead of stderr
+	captured_trace: Option<String>,
 }
 struct FileData {
 	string: Option<IStr>,
@@ -216,12 +282,47 @@ impl Breakpoints {
 	}
 }
 
-#[derive(Default)]
+thread_local! {
+	/// Number of states alive on this thread, see [`EvaluationSettings::collect_cycles_on_drop`]
+	static ALIVE_STATES: Cell<usize> = Cell::new(0);
+}
+
 pub struct EvaluationStateInternals {
 	/// Internal state
 	data: RefCell<EvaluationData>,
 	/// Settings, safe to change at runtime
 	settings: RefCell<EvaluationSettings>,
+	/// Number of thunks evaluated, `None` unless enabled with [`State::set_count_thunks_forced`].
+	/// Updated on every thunk evaluation, so it is kept out of `data` to avoid borrowing it
+	thunks_forced: Cell<Option<usize>>,
+}
+
+impl Default for EvaluationStateInternals {
+	fn default() -> Self {
+		ALIVE_STATES.with(|alive| alive.set(alive.get() + 1));
+		Self {
+			data: RefCell::default(),
+			settings: RefCell::default(),
+			thunks_forced: Cell::new(None),
+		}
+	}
+}
+
+impl Drop for EvaluationStateInternals {
+	fn drop(&mut self) {
+		let alive = ALIVE_STATES.with(|alive| {
+			alive.set(alive.get() - 1);
+			alive.get()
+		});
+		if !self.settings.get_mut().collect_cycles_on_drop || alive != 0 {
+			return;
+		}
+		// Cached files and globals should be released first, otherwise values referenced by them
+		// are still reachable during collection
+		drop(std::mem::take(self.data.get_mut()));
+		drop(std::mem::take(self.settings.get_mut()));
+		jrsonnet_gcmodule::collect_thread_cycles();
+	}
 }
 
 /// Maintains stack trace and import resolution
@@ -391,18 +492,117 @@ impl State {
 			column,
 		)
 	}
-	/// Adds standard library global variable (std) to this evaluator
+	/// Adds standard library global variable (std) to this evaluator,
+	/// its name can be changed with [`Self::set_stdlib_name`]
 	pub fn with_stdlib(&self) -> &Self {
 		let val = evaluate(
 			self.clone(),
 			self.create_default_context(),
-			&stdlib::get_parsed_stdlib(),
+			&self.stdlib_expr(),
 		)
 		.expect("std should not fail");
-		self.settings_mut().globals.insert("std".into(), val);
+		let mut settings = self.settings_mut();
+		let name = settings.stdlib_name.clone();
+		settings.globals.insert(name, val);
+		drop(settings);
 		self
 	}
 
+	/// Same as [`Self::with_stdlib`], but only binds specified standard library fields,
+	/// and the fields they depend on. Using any other field fails with an error.
+	///
+	/// This only reduces work done at startup and when looking up std fields: stdlib source is still
+	/// parsed (or deserialized) as a whole, only binding of omitted fields is skipped,
+	/// and functions which are available aren't faster to call.
+	/// Worth it only for short-lived states, which use a few well-known std functions.
+	///
+	/// Dependencies are found by walking stdlib source, if some kept field uses stdlib object
+	/// in a way which doesn't allow to know accessed fields (i.e `std[name]`), nothing is omitted
+	pub fn with_stdlib_subset(&self, fields: &[IStr]) -> &Self {
+		#[derive(Trace)]
+		struct Unavailable(IStr);
+		impl ThunkValue for Unavailable {
+			type Output = Val;
+			fn get(self: Box<Self>, _: State) -> Result<Val> {
+				throw!(StdlibFunctionUnavailable(self.0))
+			}
+		}
+
+		let (expr, omitted) = stdlib::get_parsed_stdlib_subset(&self.stdlib_expr(), fields);
+		let val = evaluate(self.clone(), self.create_default_context(), &expr)
+			.expect("std should not fail");
+		let obj = match val {
+			Val::Obj(obj) => obj,
+			_ => unreachable!("stdlib is an object"),
+		};
+		let mut builder = ObjValueBuilder::with_capacity(omitted.len());
+		builder.with_super(obj);
+		for name in omitted {
+			builder
+				.member(name.clone())
+				.hide()
+				.binding(
+					self.clone(),
+					LazyBinding::Bound(Thunk::new(tb!(Unavailable(name)))),
+				)
+				.expect("names are unique");
+		}
+		let mut settings = self.settings_mut();
+		let name = settings.stdlib_name.clone();
+		settings.globals.insert(name, Val::Obj(builder.build()));
+		drop(settings);
+		self
+	}
+
+	fn stdlib_expr(&self) -> LocExpr {
+		self.settings()
+			.custom_stdlib
+			.clone()
+			.unwrap_or_else(stdlib::get_parsed_stdlib)
+	}
+
+	/// Loads standard library source from file, instead of using the embedded one,
+	/// useful to try stdlib changes without recompiling.
+	///
+	/// Loaded file should contain an object, intrinsics are available in it as usual.
+	/// If standard library is already added, it is replaced with the loaded one
+	pub fn set_stdlib_path(&self, path: &Path) -> Result<()> {
+		let path = path.canonicalize().map_err(|e| ImportIo(e.to_string()))?;
+		let code = std::fs::read(&path).map_err(|e| ImportIo(e.to_string()))?;
+		let code: IStr = std::str::from_utf8(&code)
+			.map_err(|_| ImportBadFileUtf8(path.clone()))?
+			.into();
+		let source = Source::new(path.clone()).expect("path is canonicalized");
+		let parsed = jrsonnet_parser::parse(
+			&code,
+			&ParserSettings {
+				file_name: source.clone(),
+			},
+		)
+		.map_err(|e| ImportSyntaxError {
+			path: source,
+			source_code: code.clone(),
+			error: Box::new(e),
+		})?;
+		if !matches!(&*parsed.0, Expr::Obj(ObjBody::MemberList(_))) {
+			throw!(RuntimeError(
+				format!("stdlib {} should be an object", path.display()).into()
+			));
+		}
+		self.data_mut()
+			.files
+			.insert(path, FileData::new_string(code));
+
+		let mut settings = self.settings_mut();
+		settings.custom_stdlib = Some(parsed);
+		let loaded = settings.globals.contains_key(&settings.stdlib_name);
+		drop(settings);
+		if loaded {
+			self.with_stdlib();
+		}
+		Ok(())
+	}
+
 	/// Creates context with all passed global variables
 	pub fn create_default_context(&self) -> Context {
 		let globals = &self.settings().globals;
@@ -565,6 +765,12 @@ impl State {
 	fn data_mut(&self) -> RefMut<EvaluationData> {
 		self.0.data.borrow_mut()
 	}
+	fn count_thunk_forced(&self) {
+		let counter = &self.0.thunks_forced;
+		if let Some(forced) = counter.get() {
+			counter.set(Some(forced + 1));
+		}
+	}
 	pub fn settings(&self) -> Ref<EvaluationSettings> {
 		self.0.settings.borrow()
 	}
@@ -575,8 +781,8 @@ impl State {
 
 /// Raw methods evaluate passed values but don't perform TLA execution
 impl State {
-	/// Parses and evaluates the given snippet
-	pub fn evaluate_snippet(&self, name: String, code: String) -> Result<Val> {
+	/// Parses the given snippet, keeping its source for stack traces
+	pub fn parse_snippet(&self, name: String, code: String) -> Result<LocExpr> {
 		let source = Source::new_virtual(Cow::Owned(name.clone()));
 		let parsed = jrsonnet_parser::parse(
 			&code,
@@ -590,8 +796,58 @@ impl State {
 			error: Box::new(e),
 		})?;
 		self.data_mut().volatile_files.insert(name, code);
+		Ok(parsed)
+	}
+
+	/// Parses and evaluates the given snippet
+	pub fn evaluate_snippet(&self, name: String, code: String) -> Result<Val> {
+		let parsed = self.parse_snippet(name, code)?;
 		evaluate(self.clone(), self.create_default_context(), &parsed)
 	}
+
+	/// Same as [`Self::evaluate_snippet`], but also returns time spent, including snippet parsing.
+	///
+	/// See [`Self::evaluate_expr_timed`] for caveats
+	pub fn evaluate_snippet_timed(&self, name: String, code: String) -> Result<(Val, Duration)> {
+		let start = Instant::now();
+		let parsed = self.parse_snippet(name, code)?;
+		let (value, _) = self.evaluate_expr_timed(&parsed)?;
+		Ok((value, start.elapsed()))
+	}
+
+	/// Evaluates already parsed expression, returning its value and time spent on evaluation
+	///
+	/// This is a best-effort wall clock measurement, intended for lightweight profiling:
+	/// it includes everything happening on the thread meanwhile, and only covers evaluation of
+	/// the returned value itself, object fields and array elements stay lazy until manifestification.
+	/// Use [`Self::thunks_forced`] before and after the call to also count evaluated thunks,
+	/// counting should be enabled with [`Self::set_count_thunks_forced`] first
+	pub fn evaluate_expr_timed(&self, expr: &LocExpr) -> Result<(Val, Duration)> {
+		let start = Instant::now();
+		let value = evaluate(self.clone(), self.create_default_context(), expr)?;
+		Ok((value, start.elapsed()))
+	}
+
+	/// Returns number of lazy values evaluated with this state since counting was enabled,
+	/// or `None` if it is disabled, see [`Self::set_count_thunks_forced`].
+	///
+	/// Every value is only counted once, as its result is cached
+	pub fn thunks_forced(&self) -> Option<usize> {
+		self.0.thunks_forced.get()
+	}
+
+	/// Enables or disables counting of evaluated lazy values, returned by [`Self::thunks_forced`].
+	///
+	/// Disabled by default, as it adds a bit of work to every thunk evaluation.
+	/// Enabling already enabled counting keeps current count, disabling resets it
+	pub fn set_count_thunks_forced(&self, enabled: bool) {
+		let counter = &self.0.thunks_forced;
+		match (enabled, counter.get()) {
+			(true, None) => counter.set(Some(0)),
+			(false, _) => counter.set(None),
+			(true, Some(_)) => {}
+		}
+	}
 }
 
 /// Settings utilities
@@ -676,6 +932,10 @@ impl State {
 	pub fn add_native(&self, name: IStr, cb: Cc<TraceBox<dyn Builtin>>) {
 		self.settings_mut().ext_natives.insert(name, cb);
 	}
+	/// Names of all registered native functions, in no particular order
+	pub fn native_names(&self) -> Vec<IStr> {
+		self.settings().ext_natives.keys().cloned().collect()
+	}
 
 	pub fn manifest_format(&self) -> ManifestFormat {
 		self.settings().manifest_format.clone()
@@ -704,4 +964,78 @@ impl State {
 	pub fn set_max_stack(&self, trace: usize) {
 		self.settings_mut().max_stack = trace;
 	}
+
+	pub fn warn_precision_loss(&self) -> bool {
+		self.settings().warn_precision_loss
+	}
+	pub fn set_warn_precision_loss(&self, warn: bool) {
+		self.settings_mut().warn_precision_loss = warn;
+	}
+
+	pub fn highlight_output(&self) -> bool {
+		self.settings().highlight_output
+	}
+	pub fn set_highlight_output(&self, highlight: bool) {
+		self.settings_mut().highlight_output = highlight;
+	}
+
+	pub fn to_string_spacing(&self) -> ToStringSpacing {
+		self.settings().to_string_spacing.clone()
+	}
+	/// Sets spacing used by [`Val::to_string`], i.e `":"` key-value separator with empty
+	/// item separator makes `{a: 1, b: 2} + ''` evaluate to `{"a":1,"b":2}`
+	pub fn set_to_string_spacing(&self, spacing: ToStringSpacing) {
+		self.settings_mut().to_string_spacing = spacing;
+	}
+
+	#[cfg(feature = "dates")]
+	pub fn now(&self) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+		self.settings().now
+	}
+	/// Sets time returned by `std.dateNow()`, which fails if it isn't set.
+	///
+	/// Current time is never obtained implicitly, so results of evaluation only depend
+	/// on the passed value
+	#[cfg(feature = "dates")]
+	pub fn set_now(&self, now: Option<chrono::DateTime<chrono::FixedOffset>>) {
+		self.settings_mut().now = now;
+	}
+
+	pub fn stdlib_name(&self) -> IStr {
+		self.settings().stdlib_name.clone()
+	}
+	/// Exposes standard library under another name, i.e to allow user code to define its own `std`.
+	/// If standard library is already added, it is moved to the new name.
+	///
+	/// Standard library only refers to itself through `self`, and intrinsics are bound by their own names,
+	/// so nothing in it depends on being named `std`. Code which refers to `std` should be updated, though
+	pub fn set_stdlib_name(&self, name: IStr) {
+		let mut settings = self.settings_mut();
+		let old = std::mem::replace(&mut settings.stdlib_name, name.clone());
+		if let Some(stdlib) = settings.globals.remove(&old) {
+			settings.globals.insert(name, stdlib);
+		}
+	}
+
+	/// Start capturing `std.trace` and warning output into in-memory buffer, instead of
+	/// writing it to stderr, useful for testing libraries
+	///
+	/// Previously captured output is discarded
+	pub fn start_trace_capture(&self) {
+		self.data_mut().captured_trace = Some(String::new());
+	}
+	/// Stops capturing, returning everything written since [`Self::start_trace_capture`]
+	pub fn finish_trace_capture(&self) -> Option<String> {
+		self.data_mut().captured_trace.take()
+	}
+	/// Writes line of `std.trace`/warning output, either to capture buffer or stderr
+	pub(crate) fn write_trace_line(&self, line: &str) {
+		let mut data = self.data_mut();
+		if let Some(captured) = &mut data.captured_trace {
+			captured.push_str(line);
+			captured.push('\n');
+		} else {
+			eprintln!("{}", line);
+		}
+	}
 }
@@ -127,11 +127,20 @@ pub struct ObjValueInternals {
 	this: Option<ObjValue>,
 
 	assertions: Cc<Vec<TraceBox<dyn ObjectAssertion>>>,
-	assertions_ran: RefCell<GcHashSet<ObjValue>>,
+	/// Shared with [`ObjValue::with_this`] views, see `value_cache`
+	assertions_ran: Cc<RefCell<GcHashSet<ObjValue>>>,
 	this_entries: Cc<GcHashMap<IStr, ObjMember>>,
-	value_cache: RefCell<GcHashMap<CacheKey, CacheValue>>,
+	/// Shared with [`ObjValue::with_this`] views, as they have the same layers, and only differ
+	/// by the default `this`, which is a part of the key.
+	/// This way `super.field` doesn't reevaluate field every time `super` is referenced
+	value_cache: Cc<RefCell<GcHashMap<CacheKey, CacheValue>>>,
+	/// Names, which are known to be absent in object, including hidden fields
+	missing_cache: RefCell<GcHashSet<IStr>>,
 }
 
+/// Limits memory, used by negative field lookup cache of every object
+const MISSING_CACHE_CAPACITY: usize = 32;
+
 #[derive(Clone, Trace)]
 pub struct WeakObjValue(#[trace(skip)] pub(crate) Weak<ObjValueInternals>);
 
@@ -181,9 +190,10 @@ impl ObjValue {
 			sup,
 			this: None,
 			assertions,
-			assertions_ran: RefCell::new(GcHashSet::new()),
+			assertions_ran: Cc::new(RefCell::new(GcHashSet::new())),
 			this_entries,
-			value_cache: RefCell::new(GcHashMap::new()),
+			value_cache: Cc::new(RefCell::new(GcHashMap::new())),
+			missing_cache: RefCell::new(GcHashSet::new()),
 		}))
 	}
 	pub fn new_empty() -> Self {
@@ -218,13 +228,50 @@ impl ObjValue {
 		Self(Cc::new(ObjValueInternals {
 			sup: self.0.sup.clone(),
 			assertions: self.0.assertions.clone(),
-			assertions_ran: RefCell::new(GcHashSet::new()),
+			assertions_ran: self.0.assertions_ran.clone(),
 			this: Some(this),
 			this_entries: self.0.this_entries.clone(),
-			value_cache: RefCell::new(GcHashMap::new()),
+			value_cache: self.0.value_cache.clone(),
+			missing_cache: self.0.missing_cache.clone(),
 		}))
 	}
 
+	/// Returns object, which this object extends, i.e `a` for `a + {b: 1}`
+	///
+	/// Only object representation is inspected, no field is evaluated
+	pub fn super_object(&self) -> Option<Self> {
+		self.0.sup.clone()
+	}
+
+	/// Returns names of fields defined in this object itself, excluding fields of
+	/// [`Self::super_object`], sorted alphabetically. Hidden fields are included
+	pub fn own_fields(&self) -> Vec<IStr> {
+		let mut fields: Vec<_> = self.0.this_entries.keys().cloned().collect();
+		fields.sort_unstable();
+		fields
+	}
+
+	/// Returns layers, in which every field (including hidden) is defined
+	///
+	/// Layer 0 is the object itself, layer 1 is its [`Self::super_object`], and so on.
+	/// Layers are sorted, so the first one is where the field value comes from,
+	/// the others are inherited definitions it overrides (or refers to, using `super`).
+	///
+	/// No field is evaluated
+	pub fn field_layers(&self) -> FxHashMap<IStr, Vec<usize>> {
+		let mut out: FxHashMap<IStr, Vec<usize>> = FxHashMap::default();
+		let mut layer = Some(self.clone());
+		let mut depth = 0;
+		while let Some(obj) = layer {
+			for name in obj.0.this_entries.keys() {
+				out.entry(name.clone()).or_default().push(depth);
+			}
+			layer = obj.super_object();
+			depth += 1;
+		}
+		out
+	}
+
 	pub fn len(&self) -> usize {
 		self.fields_visibility()
 			.into_iter()
@@ -277,6 +324,13 @@ impl ObjValue {
 		});
 		out
 	}
+	/// Returns names of object fields, sorted alphabetically.
+	///
+	/// With `preserve_order`, fields are instead returned in order of definition:
+	/// fields of the base object go first, followed by new fields of every object merged into it,
+	/// in the order of merging, i.e. `(a + b) + c` and `a + (b + c)` are ordered the same way.
+	/// Overridden fields keep position of their first definition, so fields of
+	/// `{a: 1, b: 2} + {c: 3, a: 4}` are ordered as `a, b, c`
 	pub fn fields_ex(
 		&self,
 		include_hidden: bool,
@@ -319,6 +373,32 @@ impl ObjValue {
 		fields.sort_unstable();
 		fields
 	}
+	/// Returns visible and hidden fields, enumerating object only once
+	pub fn fields_split(
+		&self,
+		#[cfg(feature = "exp-preserve-order")] preserve_order: bool,
+	) -> (Vec<IStr>, Vec<IStr>) {
+		let mut fields: Vec<_> = self.fields_visibility().into_iter().collect();
+		#[cfg(feature = "exp-preserve-order")]
+		if preserve_order {
+			fields.sort_unstable_by_key(|(_, (_, sk))| *sk);
+		} else {
+			fields.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+		}
+		#[cfg(not(feature = "exp-preserve-order"))]
+		fields.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+		let mut visible = Vec::new();
+		let mut hidden = Vec::new();
+		for (name, (is_visible, _)) in fields {
+			if is_visible {
+				visible.push(name);
+			} else {
+				hidden.push(name);
+			}
+		}
+		(visible, hidden)
+	}
 	pub fn fields(&self, #[cfg(feature = "exp-preserve-order")] preserve_order: bool) -> Vec<IStr> {
 		self.fields_ex(
 			false,
@@ -357,19 +437,46 @@ impl ObjValue {
 
 	pub fn has_field_ex(&self, name: IStr, include_hidden: bool) -> bool {
 		if include_hidden {
-			self.has_field_include_hidden(name)
+			if self.0.missing_cache.borrow().contains(&name) {
+				return false;
+			}
+			let has = self.has_field_include_hidden(name.clone());
+			if !has {
+				self.remember_missing(name);
+			}
+			has
 		} else {
 			self.has_field(name)
 		}
 	}
 	pub fn has_field(&self, name: IStr) -> bool {
-		self.field_visibility(name)
-			.map_or(false, |v| v.is_visible())
+		if self.0.missing_cache.borrow().contains(&name) {
+			return false;
+		}
+		match self.field_visibility(name.clone()) {
+			Some(v) => v.is_visible(),
+			None => {
+				self.remember_missing(name);
+				false
+			}
+		}
+	}
+	fn remember_missing(&self, name: IStr) {
+		let mut cache = self.0.missing_cache.borrow_mut();
+		if cache.len() < MISSING_CACHE_CAPACITY {
+			cache.insert(name);
+		}
 	}
 
 	pub fn get(&self, s: State, key: IStr) -> Result<Option<Val>> {
-		self.run_assertions(s.clone())?;
-		self.get_raw(s, key, self.0.this.clone().unwrap_or_else(|| self.clone()))
+		let real_this = self.real_this();
+		self.run_assertions_raw(s.clone(), &real_this)?;
+		self.get_raw(s, key, real_this)
+	}
+
+	/// Object, which is used as `self` for fields of this object
+	fn real_this(&self) -> Self {
+		self.0.this.clone().unwrap_or_else(|| self.clone())
 	}
 
 	// pub fn extend_with(self, key: )
@@ -451,7 +558,7 @@ impl ObjValue {
 		Ok(())
 	}
 	pub fn run_assertions(&self, s: State) -> Result<()> {
-		self.run_assertions_raw(s, self)
+		self.run_assertions_raw(s, &self.real_this())
 	}
 
 	pub fn ptr_eq(a: &Self, b: &Self) -> bool {
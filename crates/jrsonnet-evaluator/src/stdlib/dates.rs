@@ -0,0 +1,78 @@
+//! Date builtins, operating on RFC3339 strings, i.e `2022-05-01T10:00:00Z`
+//!
+//! Real clock is never read: `std.dateNow()` returns time set by [`State::set_now`],
+//! so evaluation result only depends on its inputs.
+
+use std::fmt::Write;
+
+use chrono::{
+	format::{Item, StrftimeItems},
+	DateTime, Duration, FixedOffset, SecondsFormat,
+};
+use jrsonnet_interner::IStr;
+
+use crate::{
+	error::{Error::*, Result},
+	throw, State,
+};
+
+fn parse_date(date: &str) -> Result<DateTime<FixedOffset>> {
+	DateTime::parse_from_rfc3339(date)
+		.map_err(|e| RuntimeError(format!("invalid RFC3339 date {:?}: {}", date, e).into()).into())
+}
+
+fn format_date(date: &DateTime<FixedOffset>) -> String {
+	date.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+#[jrsonnet_macros::builtin]
+pub fn builtin_date_now(s: State) -> Result<String> {
+	match s.now() {
+		Some(now) => Ok(format_date(&now)),
+		None => throw!(RuntimeError(
+			"current time is not set, it should be provided by the caller".into()
+		)),
+	}
+}
+
+/// Returns seconds since unix epoch, with millisecond precision
+#[jrsonnet_macros::builtin]
+pub fn builtin_date_parse(date: IStr) -> Result<f64> {
+	Ok(parse_date(&date)?.timestamp_millis() as f64 / 1000.0)
+}
+
+/// Formats date using strftime-like `format`, see [`chrono::format::strftime`]
+#[jrsonnet_macros::builtin]
+pub fn builtin_date_format(date: IStr, format: IStr) -> Result<String> {
+	let date = parse_date(&date)?;
+	let items = StrftimeItems::new(&format).collect::<Vec<_>>();
+	if items.iter().any(|i| matches!(i, Item::Error)) {
+		throw!(RuntimeError(
+			format!("invalid date format {:?}", format.as_str()).into()
+		));
+	}
+	let mut out = String::new();
+	write!(out, "{}", date.format_with_items(items.into_iter())).map_err(|_| {
+		RuntimeError(format!("can't format date with {:?}", format.as_str()).into())
+	})?;
+	Ok(out)
+}
+
+/// Shifts date by specified number of seconds, keeping its offset
+#[jrsonnet_macros::builtin]
+pub fn builtin_date_add(date: IStr, seconds: f64) -> Result<String> {
+	let parsed = parse_date(&date)?;
+	let millis = (seconds * 1000.0).round();
+	// Larger shifts are out of supported date range anyway, and don't fit into Duration
+	let shifted = if millis.abs() < 1e18 {
+		parsed.checked_add_signed(Duration::milliseconds(millis as i64))
+	} else {
+		None
+	};
+	match shifted {
+		Some(shifted) => Ok(format_date(&shifted)),
+		None => throw!(RuntimeError(
+			format!("date {:?} + {}s is out of range", date.as_str(), seconds).into()
+		)),
+	}
+}
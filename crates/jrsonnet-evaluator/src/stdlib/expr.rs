@@ -1,6 +1,14 @@
-use std::borrow::Cow;
+use std::{
+	borrow::Cow,
+	collections::{HashMap, HashSet},
+	rc::Rc,
+};
 
-use jrsonnet_parser::{LocExpr, ParserSettings, Source};
+use jrsonnet_interner::IStr;
+use jrsonnet_parser::{
+	AssertStmt, BindSpec, Destruct, Expr, FieldMember, FieldName, LiteralType, LocExpr, Member,
+	ObjBody, Param, ParamsDesc, ParserSettings, Source,
+};
 
 thread_local! {
 	/// To avoid parsing again when issued from the same thread
@@ -26,3 +34,199 @@ thread_local! {
 pub fn get_parsed_stdlib() -> LocExpr {
 	PARSED_STDLIB.with(Clone::clone)
 }
+
+/// References to the standard library object, found in its own source
+#[derive(Default)]
+struct StdReferences {
+	/// Fields accessed by constant name, i.e `std.name`, `$.name` or `self['name']`
+	fields: Vec<IStr>,
+	/// Object is used in any other way, i.e `local s = std; s.name` or `std[name]`,
+	/// so any of its fields may be accessed
+	escaped: bool,
+}
+impl StdReferences {
+	/// Names referring to the standard library object: `std`, aliases bound in its locals,
+	/// and `self`, `super` and `$`. Inner objects have their own `self`,
+	/// treating it as reference to stdlib only makes more fields kept, which is safe
+	fn is_std(expr: &LocExpr, aliases: &HashSet<IStr>) -> bool {
+		match &*expr.0 {
+			Expr::Var(v) => aliases.contains(v),
+			Expr::Literal(LiteralType::This | LiteralType::Super | LiteralType::Dollar) => true,
+			_ => false,
+		}
+	}
+
+	fn collect(&mut self, expr: &LocExpr, aliases: &HashSet<IStr>) {
+		// Every reference should be the indexed object of `std.name`, otherwise it escapes
+		let mut references = 0;
+		let mut indexed = 0;
+		expr.walk(&mut |e| {
+			if Self::is_std(e, aliases) {
+				references += 1;
+			}
+			if let Expr::Index(obj, field) = &*e.0 {
+				if let (true, Expr::Str(name)) = (Self::is_std(obj, aliases), &*field.0) {
+					indexed += 1;
+					self.fields.push(name.clone());
+				}
+			}
+		});
+		if references != indexed {
+			self.escaped = true;
+		}
+	}
+
+	/// Collects references from method body and default values of its parameters
+	fn collect_method(
+		&mut self,
+		params: Option<&ParamsDesc>,
+		value: &LocExpr,
+		aliases: &HashSet<IStr>,
+	) {
+		for Param(_, default) in params.iter().flat_map(|p| p.iter()) {
+			if let Some(default) = default {
+				self.collect(default, aliases);
+			}
+		}
+		self.collect(value, aliases);
+	}
+}
+
+/// Returns standard library, only containing specified fields, and fields they depend on,
+/// along with names of the omitted fields.
+///
+/// Dependencies are found by walking field values, if stdlib object is referenced in a way
+/// which doesn't allow to know accessed fields, all of them are kept
+pub fn get_parsed_stdlib_subset(stdlib: &LocExpr, fields: &[IStr]) -> (LocExpr, Vec<IStr>) {
+	let members = match &*stdlib.0 {
+		Expr::Obj(ObjBody::MemberList(members)) => members,
+		_ => unreachable!("stdlib is an object"),
+	};
+
+	// `local std = self` is an alias, not an escaping reference
+	let mut aliases = HashSet::new();
+	aliases.insert(IStr::from("std"));
+	for member in members {
+		if let Member::BindStmt(BindSpec::Field {
+			into: Destruct::Full(name),
+			value,
+		}) = member
+		{
+			if StdReferences::is_std(value, &aliases) {
+				aliases.insert(name.clone());
+			}
+		}
+	}
+
+	let mut values = HashMap::new();
+	// Locals are lazy, but they are shared by all fields, so their dependencies are always kept
+	let mut references = StdReferences::default();
+	for member in members {
+		match member {
+			Member::Field(FieldMember {
+				name: FieldName::Fixed(name),
+				params,
+				value,
+				..
+			}) => {
+				values.insert(name.clone(), (params.as_ref(), value));
+			}
+			Member::Field(FieldMember {
+				name: FieldName::Dyn(name),
+				params,
+				value,
+				..
+			}) => {
+				references.collect(name, &aliases);
+				references.collect_method(params.as_ref(), value, &aliases);
+			}
+			Member::BindStmt(BindSpec::Field {
+				into: Destruct::Full(name),
+				value,
+			}) if aliases.contains(name) && StdReferences::is_std(value, &aliases) => {}
+			Member::BindStmt(BindSpec::Field { value, .. }) => references.collect(value, &aliases),
+			Member::BindStmt(BindSpec::Function { params, value, .. }) => {
+				references.collect_method(Some(params), value, &aliases);
+			}
+			Member::AssertStmt(AssertStmt(cond, message)) => {
+				references.collect(cond, &aliases);
+				if let Some(message) = message {
+					references.collect(message, &aliases);
+				}
+			}
+		}
+	}
+	let mut pending = std::mem::take(&mut references.fields);
+	pending.extend(fields.iter().cloned());
+
+	let mut kept = HashSet::new();
+	while let Some(name) = pending.pop() {
+		if !kept.insert(name.clone()) {
+			continue;
+		}
+		if let Some((params, value)) = values.get(&name) {
+			references.collect_method(*params, value, &aliases);
+			pending.append(&mut references.fields);
+		}
+	}
+	if references.escaped {
+		return (stdlib.clone(), Vec::new());
+	}
+
+	let mut omitted = Vec::new();
+	let members = members
+		.iter()
+		.filter(|member| match member {
+			Member::Field(FieldMember {
+				name: FieldName::Fixed(name),
+				..
+			}) => {
+				let keep = kept.contains(name);
+				if !keep {
+					omitted.push(name.clone());
+				}
+				keep
+			}
+			_ => true,
+		})
+		.cloned()
+		.collect();
+	(
+		LocExpr(
+			Rc::new(Expr::Obj(ObjBody::MemberList(members))),
+			stdlib.1.clone(),
+		),
+		omitted,
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn omitted(fields: &[&str]) -> Vec<String> {
+		let stdlib = jrsonnet_parser::parse(
+			"{ local s = self, a: s.b, b: 1, c: 2, d:: $.c, e(x):: std[x], f(x=self.g):: x, g: 3 }",
+			&ParserSettings {
+				file_name: Source::new_virtual(Cow::Borrowed("<test>")),
+			},
+		)
+		.unwrap();
+		let fields: Vec<IStr> = fields.iter().map(|&f| f.into()).collect();
+		let (_, omitted) = get_parsed_stdlib_subset(&stdlib, &fields);
+		let mut omitted: Vec<String> = omitted.iter().map(ToString::to_string).collect();
+		omitted.sort();
+		omitted
+	}
+
+	#[test]
+	fn dependencies() {
+		assert_eq!(omitted(&["a"]), ["c", "d", "e", "f", "g"]);
+		assert_eq!(omitted(&["d", "f"]), ["a", "b", "e"]);
+	}
+
+	#[test]
+	fn escaped_reference_keeps_everything() {
+		assert!(omitted(&["e"]).is_empty());
+	}
+}
@@ -1,6 +1,17 @@
+use std::{
+	io,
+	ops::{Deref, DerefMut},
+};
+
+use jrsonnet_interner::IStr;
+use rustc_hash::FxHashMap;
+
 use crate::{
 	error::{Error::*, Result},
-	throw, State, Val,
+	stdlib::sort::sort_keyed,
+	throw,
+	val::ArrValue,
+	ObjValue, State, Val,
 };
 
 #[derive(PartialEq, Eq, Clone, Copy)]
@@ -16,42 +27,478 @@ pub enum ManifestType {
 	Minify,
 }
 
+/// How functions are manifested, as they have no representation in output formats
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum FunctionManifest {
+	/// Fail manifestification
+	Error,
+	/// Write specified string in place of function
+	Placeholder(String),
+	/// Omit object fields holding functions, functions in other positions are written as `null`
+	Skip,
+}
+impl Default for FunctionManifest {
+	fn default() -> Self {
+		Self::Error
+	}
+}
+
+/// How numbers are written in json output
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum NumberFormat {
+	/// Same as reference implementations, see [`format_number`]
+	Default,
+	/// Same as python `repr(float)` (and thus `json.dumps`): shortest roundtrip digits,
+	/// whole numbers are suffixed with `.0`, and exponent notation is used for exponents below -4 or above 15
+	///
+	/// Python writes integers without `.0`, so values which are `int` on python side
+	/// will only match when [`Self::Default`] is used
+	Python,
+}
+impl Default for NumberFormat {
+	fn default() -> Self {
+		Self::Default
+	}
+}
+
+/// Writes number byte-compatible with go-jsonnet and C++ jsonnet `unparseNumber`:
+/// whole numbers are written in full without fraction part (`%.0f`), others are written
+/// with 17 significant digits (`%.17g`), which is enough for any f64 to roundtrip.
+///
+/// I.e `1e21` is written as `1000000000000000000000`, `0.1` as `0.10000000000000001`,
+/// and `5e-324` as `4.9406564584124654e-324`
+pub fn format_number(buf: &mut String, n: f64) {
+	use std::fmt::Write;
+	const PRECISION: i32 = 17;
+
+	// Non-finite numbers have no exponent, and are written as is
+	if !n.is_finite() || n.fract() == 0.0 {
+		write!(buf, "{:.0}", n).unwrap();
+		return;
+	}
+	let sci = format!("{:.*e}", PRECISION as usize - 1, n);
+	let (mantissa, exp) = sci.split_once('e').expect("exponent is always present");
+	let exp: i32 = exp.parse().expect("exponent is a number");
+	let trim = |v: &str| v.trim_end_matches('0').trim_end_matches('.').to_owned();
+	if exp < -4 || exp >= PRECISION {
+		buf.push_str(&trim(mantissa));
+		buf.push('e');
+		buf.push(if exp < 0 { '-' } else { '+' });
+		if exp.abs() < 10 {
+			buf.push('0');
+		}
+		write!(buf, "{}", exp.abs()).unwrap();
+	} else {
+		buf.push_str(&trim(&format!("{:.*}", (PRECISION - 1 - exp) as usize, n)));
+	}
+}
+
+/// Writes number the same way as python `float.__repr__`
+///
+/// Both python and rust produce the shortest digit sequence which roundtrips,
+/// picking the closest one when there is a choice, so only the layout needs to be replicated
+fn write_python_float(buf: &mut String, n: f64) {
+	if n.is_sign_negative() {
+		buf.push('-');
+	}
+	let sci = format!("{:e}", n.abs());
+	let (mantissa, exp) = sci.split_once('e').expect("exponent is always present");
+	let exp: i32 = exp.parse().expect("exponent is a number");
+	let digits = mantissa.replace('.', "");
+	// Position of decimal point relative to the start of digits
+	let decpt = exp + 1;
+	if decpt <= -4 || decpt > 16 {
+		buf.push_str(mantissa);
+		buf.push('e');
+		buf.push(if exp < 0 { '-' } else { '+' });
+		if exp.abs() < 10 {
+			buf.push('0');
+		}
+		buf.push_str(&exp.abs().to_string());
+	} else if decpt <= 0 {
+		buf.push_str("0.");
+		for _ in 0..-decpt {
+			buf.push('0');
+		}
+		buf.push_str(&digits);
+	} else if decpt as usize >= digits.len() {
+		buf.push_str(&digits);
+		for _ in digits.len()..decpt as usize {
+			buf.push('0');
+		}
+		buf.push_str(".0");
+	} else {
+		let (int, frac) = digits.split_at(decpt as usize);
+		buf.push_str(int);
+		buf.push('.');
+		buf.push_str(frac);
+	}
+}
+
+/// Filters out fields holding functions, for [`FunctionManifest::Skip`]
+fn without_function_fields(s: State, obj: &ObjValue, fields: Vec<IStr>) -> Result<Vec<IStr>> {
+	let mut out = Vec::with_capacity(fields.len());
+	for field in fields {
+		let value = s.push_description(
+			|| format!("field <{}> manifestification", field.clone()),
+			|| Ok(obj.get(s.clone(), field.clone())?.expect("field exists")),
+		)?;
+		if !matches!(value, Val::Func(_)) {
+			out.push(field);
+		}
+	}
+	Ok(out)
+}
+
+/// Kind of text written by json manifestification
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum JsonToken {
+	/// Object field name, including quotes
+	Key,
+	/// String value, including quotes
+	String,
+	Number,
+	/// `true`, `false` and `null`
+	Literal,
+	/// Brackets, braces, commas and key-value separators
+	Punctuation,
+	/// `//` comment line, see [`ManifestJsonOptions::comments`]
+	Comment,
+}
+
+/// Allows to decorate json output, i.e to highlight it for terminal.
+///
+/// Every token is written between [`Self::start`] and [`Self::end`] calls, whitespace is written as is
+pub trait JsonStyler {
+	fn start(&self, _token: JsonToken, _buf: &mut String) {}
+	fn end(&self, _token: JsonToken, _buf: &mut String) {}
+}
+
+/// Writes json without decorations
+pub struct NoStyle;
+impl JsonStyler for NoStyle {}
+
+/// Highlights json with ANSI color escape codes, punctuation is left uncolored
+pub struct AnsiStyler;
+impl JsonStyler for AnsiStyler {
+	fn start(&self, token: JsonToken, buf: &mut String) {
+		buf.push_str(match token {
+			JsonToken::Key => "\x1b[34m",
+			JsonToken::String => "\x1b[32m",
+			JsonToken::Number => "\x1b[33m",
+			JsonToken::Literal => "\x1b[35m",
+			JsonToken::Comment => "\x1b[90m",
+			JsonToken::Punctuation => return,
+		});
+	}
+	fn end(&self, token: JsonToken, buf: &mut String) {
+		if token != JsonToken::Punctuation {
+			buf.push_str("\x1b[0m");
+		}
+	}
+}
+
+fn write_token(
+	buf: &mut String,
+	options: &ManifestJsonOptions<'_>,
+	token: JsonToken,
+	write: impl FnOnce(&mut String),
+) {
+	options.styler.start(token, buf);
+	write(buf);
+	options.styler.end(token, buf);
+}
+
 pub struct ManifestJsonOptions<'s> {
 	pub padding: &'s str,
 	pub mtype: ManifestType,
 	pub newline: &'s str,
 	pub key_val_sep: &'s str,
-	#[cfg(feature = "exp-preserve-order")]
-	pub preserve_order: bool,
+	/// Written after `,` between array elements and object fields in [`ManifestType::ToString`] output
+	pub item_sep: &'s str,
+	/// Written between brackets of empty arrays and objects in [`ManifestType::ToString`] output
+	pub empty_padding: &'s str,
+	/// Arrays and objects nested deeper than this are manifested minified,
+	/// ignoring `padding` and `newline`, and with whitespace trimmed from `key_val_sep`
+	///
+	/// Top-level value has depth 0, so `Some(1)` only pretty-prints the outermost value
+	pub max_depth: Option<usize>,
+	/// Write non-finite numbers as `Infinity`, `-Infinity` and `NaN`, as accepted by i.e python,
+	/// instead of failing. Output is not valid json with this option enabled
+	pub allow_non_finite: bool,
+	/// Arrays, consisting only of objects having this visible field, are manifested sorted by its value.
+	/// Only affects output, evaluation semantics stay the same.
+	///
+	/// Same as for `std.sort`, values of this field should be either all strings or all numbers,
+	/// otherwise manifestification fails with sort error
+	pub sort_arrays_by: Option<&'s str>,
+	/// Escape DEL and C1 control characters (`U+007F`-`U+009F`) as `\uXXXX`, as jrsonnet always did.
+	/// Json doesn't require that, disable to write these characters as is
+	pub escape_c1: bool,
+	/// Escape every non-ASCII character as `\uXXXX`, using surrogate pairs for characters
+	/// outside of the basic multilingual plane, for consumers which only accept ASCII json.
+	/// Disabled by default, printable non-ASCII characters are written as is
+	pub ascii_only: bool,
+	/// Write comma after the last element of non-empty arrays and objects, as allowed by JSON5/JSONC,
+	/// which makes diffs smaller. Only affects multiline output, which is not valid json then
+	pub trailing_comma: bool,
+	/// Write string fields named `__comment_<key>__` as `// comment` lines above `<key>`,
+	/// producing JSONC. Comments are only written in multiline output, in single line output
+	/// such fields are omitted
+	pub comments: bool,
+	/// Fail on field names containing control characters, instead of escaping them same way as
+	/// values. Such names are valid, but dynamically computed ones are most likely a bug
+	pub validate_field_names: bool,
+	pub functions: &'s FunctionManifest,
+	/// Separate fields of top-level object with an empty line, has no effect on minified output
+	pub top_level_blank_lines: bool,
+	pub number_format: NumberFormat,
+	/// `Some(true)` keeps fields in authoring order, `Some(false)` or unset sorts them,
+	/// same as `preserve_order` argument of std manifest functions defaults to `false`.
+	/// Authoring order is only tracked with `exp-preserve-order` feature, without it
+	/// requesting it fails manifestification
+	pub preserve_order: Option<bool>,
+	/// Decorates written tokens, [`NoStyle`] keeps output as is
+	pub styler: &'s dyn JsonStyler,
+}
+/// Same options as used by `std.manifestJson`, so only fields which differ from it need to be
+/// specified, i.e `ManifestJsonOptions { padding: "", ..Default::default() }`
+impl Default for ManifestJsonOptions<'_> {
+	fn default() -> Self {
+		Self {
+			padding: "    ",
+			mtype: ManifestType::Std,
+			newline: "\n",
+			key_val_sep: ": ",
+			item_sep: " ",
+			empty_padding: " ",
+			max_depth: None,
+			allow_non_finite: false,
+			sort_arrays_by: None,
+			escape_c1: true,
+			ascii_only: false,
+			trailing_comma: false,
+			comments: false,
+			validate_field_names: false,
+			functions: &FunctionManifest::Error,
+			top_level_blank_lines: false,
+			number_format: NumberFormat::Default,
+			preserve_order: None,
+			styler: &NoStyle,
+		}
+	}
+}
+impl ManifestJsonOptions<'_> {
+	fn preserve_order(&self) -> Result<bool> {
+		resolve_preserve_order(self.preserve_order)
+	}
+}
+
+pub(crate) fn resolve_preserve_order(preserve_order: Option<bool>) -> Result<bool> {
+	#[cfg(not(feature = "exp-preserve-order"))]
+	if preserve_order == Some(true) {
+		throw!(RuntimeError(
+			"preserving field order requires exp-preserve-order feature".into()
+		));
+	}
+	Ok(preserve_order.unwrap_or(false))
+}
+
+/// Destination of manifestification output. Text is accumulated in memory, and,
+/// when writer is present, flushed to it in chunks after array elements and object fields
+struct ManifestOut<'w> {
+	buf: String,
+	writer: Option<&'w mut dyn io::Write>,
+	/// Arrays being manifested, array may contain itself when its element refers to it
+	arrays: Vec<ArrValue>,
+}
+impl<'w> ManifestOut<'w> {
+	/// Avoid issuing many tiny writes for small values
+	const FLUSH_THRESHOLD: usize = 8 * 1024;
+
+	fn new(writer: Option<&'w mut dyn io::Write>) -> Self {
+		Self {
+			buf: String::new(),
+			writer,
+			arrays: Vec::new(),
+		}
+	}
+
+	/// Fails if array is already being manifested, as its manifestification would never end
+	fn enter_array(&mut self, arr: &ArrValue) -> Result<()> {
+		if self.arrays.iter().any(|a| ArrValue::ptr_eq(a, arr)) {
+			throw!(InfiniteManifestRecursion);
+		}
+		self.arrays.push(arr.clone());
+		Ok(())
+	}
+	fn exit_array(&mut self) {
+		self.arrays.pop();
+	}
+
+	fn flush_if_needed(&mut self) -> Result<()> {
+		if self.buf.len() >= Self::FLUSH_THRESHOLD {
+			self.flush()?;
+		}
+		Ok(())
+	}
+
+	fn flush(&mut self) -> Result<()> {
+		if let Some(writer) = &mut self.writer {
+			writer
+				.write_all(self.buf.as_bytes())
+				.and_then(|()| writer.flush())
+				.map_err(|e| {
+					RuntimeError(format!("failed to write manifest output: {}", e).into())
+				})?;
+			self.buf.clear();
+		}
+		Ok(())
+	}
+}
+impl Deref for ManifestOut<'_> {
+	type Target = String;
+	fn deref(&self) -> &Self::Target {
+		&self.buf
+	}
+}
+impl DerefMut for ManifestOut<'_> {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.buf
+	}
+}
+
+/// Integers above this value can't be represented exactly by f64
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+/// Warns if `n` may lose precision, `write` should write number the same way as manifested output
+fn warn_precision_loss(s: &State, n: f64, write: impl FnOnce(&mut String)) {
+	if !s.warn_precision_loss() {
+		return;
+	}
+	let mut written = String::new();
+	write(&mut written);
+	if let Some(warning) = precision_loss_warning(n, &written) {
+		s.write_trace_line(&warning);
+	}
+}
+
+/// Integers beyond 2^53 are reported even if `written` is parsed back exactly,
+/// as they are likely results of computations on already mangled values
+// Parsed value is expected to be exactly the same
+#[allow(clippy::float_cmp)]
+fn precision_loss_warning(n: f64, written: &str) -> Option<String> {
+	if n.abs() > MAX_SAFE_INTEGER {
+		Some(format!(
+			"WARNING: number {} exceeds 2^53, integer precision may be lost",
+			n
+		))
+	} else if !matches!(written.parse::<f64>(), Ok(parsed) if parsed == n) {
+		Some(format!(
+			"WARNING: number {} is manifested as {}, which is parsed back as a different value",
+			n, written
+		))
+	} else {
+		None
+	}
 }
 
 pub fn manifest_json_ex(s: State, val: &Val, options: &ManifestJsonOptions<'_>) -> Result<String> {
-	let mut out = String::new();
-	manifest_json_ex_buf(s, val, &mut out, &mut String::new(), options)?;
-	Ok(out)
+	let mut out = ManifestOut::new(None);
+	manifest_json_ex_buf(s, val, &mut out, &mut String::new(), options, 0)?;
+	Ok(out.buf)
+}
+/// Same as [`manifest_json_ex`], but output is written to `writer` while it is being produced.
+///
+/// On error, part of the output may already be written, and the rest is discarded,
+/// so the writer contents should be considered invalid
+pub fn manifest_json_ex_to(
+	s: State,
+	val: &Val,
+	writer: &mut dyn io::Write,
+	options: &ManifestJsonOptions<'_>,
+) -> Result<()> {
+	let mut out = ManifestOut::new(Some(writer));
+	manifest_json_ex_buf(s, val, &mut out, &mut String::new(), options, 0)?;
+	out.flush()
 }
 fn manifest_json_ex_buf(
 	s: State,
 	val: &Val,
-	buf: &mut String,
+	buf: &mut ManifestOut<'_>,
 	cur_padding: &mut String,
 	options: &ManifestJsonOptions<'_>,
+	depth: usize,
 ) -> Result<()> {
-	use std::fmt::Write;
 	let mtype = options.mtype;
+	if mtype != ManifestType::Minify
+		&& options
+			.max_depth
+			.map_or(false, |max_depth| depth >= max_depth)
+		&& matches!(val, Val::Arr(_) | Val::Obj(_))
+	{
+		return manifest_json_ex_buf(
+			s,
+			val,
+			buf,
+			&mut String::new(),
+			&ManifestJsonOptions {
+				padding: "",
+				mtype: ManifestType::Minify,
+				newline: "",
+				key_val_sep: options.key_val_sep.trim(),
+				max_depth: None,
+				..*options
+			},
+			depth,
+		);
+	}
 	match val {
-		Val::Bool(v) => {
-			if *v {
-				buf.push_str("true");
-			} else {
-				buf.push_str("false");
+		Val::Bool(v) => write_token(buf, options, JsonToken::Literal, |buf| {
+			buf.push_str(if *v { "true" } else { "false" });
+		}),
+		Val::Null => write_token(buf, options, JsonToken::Literal, |buf| buf.push_str("null")),
+		Val::Str(s) => write_token(buf, options, JsonToken::String, |buf| {
+			escape_string_json_buf(s, buf, options.escape_c1, options.ascii_only);
+		}),
+		Val::Num(n) if !n.is_finite() => {
+			if !options.allow_non_finite {
+				throw!(RuntimeError(
+					format!("tried to manifest non-finite number: {}", n).into()
+				))
 			}
+			write_token(buf, options, JsonToken::Number, |buf| {
+				buf.push_str(if n.is_nan() {
+					"NaN"
+				} else if n.is_sign_positive() {
+					"Infinity"
+				} else {
+					"-Infinity"
+				});
+			});
+		}
+		Val::Num(n) => {
+			let write = |buf: &mut String| match options.number_format {
+				NumberFormat::Default => format_number(buf, *n),
+				NumberFormat::Python => write_python_float(buf, *n),
+			};
+			warn_precision_loss(&s, *n, write);
+			write_token(buf, options, JsonToken::Number, write);
 		}
-		Val::Null => buf.push_str("null"),
-		Val::Str(s) => escape_string_json_buf(s, buf),
-		Val::Num(n) => write!(buf, "{}", n).unwrap(),
 		Val::Arr(items) => {
-			buf.push('[');
+			buf.enter_array(items)?;
+			let sorted;
+			let items = match options.sort_arrays_by {
+				Some(field) => match sort_objects_by_field(s.clone(), items, field)? {
+					Some(v) => {
+						sorted = v;
+						&sorted
+					}
+					None => items,
+				},
+				None => items,
+			};
+			write_token(buf, options, JsonToken::Punctuation, |buf| buf.push('['));
 			if !items.is_empty() {
 				if mtype != ManifestType::ToString && mtype != ManifestType::Minify {
 					buf.push_str(options.newline);
@@ -61,37 +508,57 @@ fn manifest_json_ex_buf(
 				cur_padding.push_str(options.padding);
 				for (i, item) in items.iter(s.clone()).enumerate() {
 					if i != 0 {
-						buf.push(',');
+						write_token(buf, options, JsonToken::Punctuation, |buf| buf.push(','));
 						if mtype == ManifestType::ToString {
-							buf.push(' ');
+							buf.push_str(options.item_sep);
 						} else if mtype != ManifestType::Minify {
 							buf.push_str(options.newline);
 						}
 					}
 					buf.push_str(cur_padding);
-					manifest_json_ex_buf(s.clone(), &item?, buf, cur_padding, options)?;
+					manifest_json_ex_buf(s.clone(), &item?, buf, cur_padding, options, depth + 1)?;
+					buf.flush_if_needed()?;
 				}
 				cur_padding.truncate(old_len);
 
 				if mtype != ManifestType::ToString && mtype != ManifestType::Minify {
+					if options.trailing_comma && !options.newline.is_empty() {
+						write_token(buf, options, JsonToken::Punctuation, |buf| buf.push(','));
+					}
 					buf.push_str(options.newline);
 					buf.push_str(cur_padding);
 				}
 			} else if mtype == ManifestType::Std {
-				buf.push_str("\n\n");
+				buf.push_str(options.newline);
+				buf.push_str(options.newline);
 				buf.push_str(cur_padding);
-			} else if mtype == ManifestType::ToString || mtype == ManifestType::Manifest {
+			} else if mtype == ManifestType::ToString {
+				buf.push_str(options.empty_padding);
+			} else if mtype == ManifestType::Manifest {
 				buf.push(' ');
 			}
-			buf.push(']');
+			write_token(buf, options, JsonToken::Punctuation, |buf| buf.push(']'));
+			buf.exit_array();
 		}
 		Val::Obj(obj) => {
 			obj.run_assertions(s.clone())?;
-			buf.push('{');
-			let fields = obj.fields(
+			write_token(buf, options, JsonToken::Punctuation, |buf| buf.push('{'));
+			#[cfg_attr(not(feature = "exp-preserve-order"), allow(unused_variables))]
+			let preserve_order = options.preserve_order()?;
+			let mut fields = obj.fields(
 				#[cfg(feature = "exp-preserve-order")]
-				options.preserve_order,
+				preserve_order,
 			);
+			if *options.functions == FunctionManifest::Skip {
+				fields = without_function_fields(s.clone(), obj, fields)?;
+			}
+			let comments = if options.comments {
+				let (rest, comments) = split_comment_fields(obj, fields)?;
+				fields = rest;
+				comments
+			} else {
+				FxHashMap::default()
+			};
 			if !fields.is_empty() {
 				if mtype != ManifestType::ToString && mtype != ManifestType::Minify {
 					buf.push_str(options.newline);
@@ -101,51 +568,127 @@ fn manifest_json_ex_buf(
 				cur_padding.push_str(options.padding);
 				for (i, field) in fields.into_iter().enumerate() {
 					if i != 0 {
-						buf.push(',');
+						write_token(buf, options, JsonToken::Punctuation, |buf| buf.push(','));
 						if mtype == ManifestType::ToString {
-							buf.push(' ');
+							buf.push_str(options.item_sep);
 						} else if mtype != ManifestType::Minify {
 							buf.push_str(options.newline);
+							if depth == 0 && options.top_level_blank_lines {
+								buf.push_str(options.newline);
+							}
 						}
 					}
+					if options.validate_field_names {
+						validate_field_name(&field)?;
+					}
 					buf.push_str(cur_padding);
-					escape_string_json_buf(&field, buf);
-					buf.push_str(options.key_val_sep);
+					if let Some(comment) = comments.get(&field) {
+						if !options.newline.is_empty() {
+							let comment = comment_text(s.clone(), obj, comment)?;
+							for line in comment.lines() {
+								write_token(buf, options, JsonToken::Comment, |buf| {
+									buf.push_str("//");
+									if !line.is_empty() {
+										buf.push(' ');
+										// Other line terminators would end comment early
+										buf.extend(line.chars().map(|c| match c {
+											'\r' | '\u{2028}' | '\u{2029}' => ' ',
+											c => c,
+										}));
+									}
+								});
+								buf.push_str(options.newline);
+								buf.push_str(cur_padding);
+							}
+						}
+					}
+					write_token(buf, options, JsonToken::Key, |buf| {
+						escape_string_json_buf(&field, buf, options.escape_c1, options.ascii_only);
+					});
+					write_token(buf, options, JsonToken::Punctuation, |buf| {
+						buf.push_str(options.key_val_sep);
+					});
 					s.push_description(
 						|| format!("field <{}> manifestification", field.clone()),
 						|| {
 							let value = obj.get(s.clone(), field.clone())?.unwrap();
-							manifest_json_ex_buf(s.clone(), &value, buf, cur_padding, options)?;
+							manifest_json_ex_buf(
+								s.clone(),
+								&value,
+								buf,
+								cur_padding,
+								options,
+								depth + 1,
+							)?;
 							Ok(Val::Null)
 						},
 					)?;
+					buf.flush_if_needed()?;
 				}
 				cur_padding.truncate(old_len);
 
 				if mtype != ManifestType::ToString && mtype != ManifestType::Minify {
+					if options.trailing_comma && !options.newline.is_empty() {
+						write_token(buf, options, JsonToken::Punctuation, |buf| buf.push(','));
+					}
 					buf.push_str(options.newline);
 					buf.push_str(cur_padding);
 				}
 			} else if mtype == ManifestType::Std {
-				buf.push_str("\n\n");
+				buf.push_str(options.newline);
+				buf.push_str(options.newline);
 				buf.push_str(cur_padding);
-			} else if mtype == ManifestType::ToString || mtype == ManifestType::Manifest {
+			} else if mtype == ManifestType::ToString {
+				buf.push_str(options.empty_padding);
+			} else if mtype == ManifestType::Manifest {
 				buf.push(' ');
 			}
-			buf.push('}');
+			write_token(buf, options, JsonToken::Punctuation, |buf| buf.push('}'));
 		}
-		Val::Func(_) => throw!(RuntimeError("tried to manifest function".into())),
+		Val::Func(_) => match options.functions {
+			FunctionManifest::Error => throw!(RuntimeError("tried to manifest function".into())),
+			FunctionManifest::Placeholder(placeholder) => {
+				write_token(buf, options, JsonToken::String, |buf| {
+					escape_string_json_buf(placeholder, buf, options.escape_c1, options.ascii_only);
+				});
+			}
+			FunctionManifest::Skip => {
+				write_token(buf, options, JsonToken::Literal, |buf| buf.push_str("null"));
+			}
+		},
 	};
 	Ok(())
 }
 
+/// Returns sorted array, if all of its elements are objects having specified visible field
+fn sort_objects_by_field(s: State, items: &ArrValue, field: &str) -> Result<Option<ArrValue>> {
+	let field = IStr::from(field);
+	let mut keyed = Vec::with_capacity(items.len());
+	for item in items.iter(s.clone()) {
+		match item? {
+			Val::Obj(obj) if obj.has_field(field.clone()) => {
+				let key = obj.get(s.clone(), field.clone())?.expect("field exists");
+				keyed.push((Val::Obj(obj), key));
+			}
+			_ => return Ok(None),
+		}
+	}
+	Ok(Some(sort_keyed(keyed)?.into()))
+}
+
 pub fn escape_string_json(s: &str) -> String {
+	escape_string_json_with(s, true)
+}
+
+/// Same as [`escape_string_json`], but DEL and C1 control characters are only escaped
+/// if `escape_c1` is set, see [`ManifestJsonOptions::escape_c1`]
+pub fn escape_string_json_with(s: &str, escape_c1: bool) -> String {
 	let mut buf = String::new();
-	escape_string_json_buf(s, &mut buf);
+	escape_string_json_buf(s, &mut buf, escape_c1, false);
 	buf
 }
 
-fn escape_string_json_buf(s: &str, buf: &mut String) {
+fn escape_string_json_buf(s: &str, buf: &mut String, escape_c1: bool, ascii_only: bool) {
 	use std::fmt::Write;
 	buf.push('"');
 	for c in s.chars() {
@@ -157,15 +700,21 @@ fn escape_string_json_buf(s: &str, buf: &mut String) {
 			'\n' => buf.push_str("\\n"),
 			'\r' => buf.push_str("\\r"),
 			'\t' => buf.push_str("\\t"),
-			c if c < 32 as char || (c >= 127 as char && c <= 159 as char) => {
+			c if c < 32 as char || (escape_c1 && (127 as char..=159 as char).contains(&c)) => {
 				write!(buf, "\\u{:04x}", c as u32).unwrap();
 			}
+			c if ascii_only && !c.is_ascii() => {
+				for unit in c.encode_utf16(&mut [0; 2]) {
+					write!(buf, "\\u{:04x}", unit).unwrap();
+				}
+			}
 			c => buf.push(c),
 		}
 	}
 	buf.push('"');
 }
 
+#[cfg(feature = "yaml")]
 pub struct ManifestYamlOptions<'s> {
 	/// Padding before fields, i.e
 	/// ```yaml
@@ -188,12 +737,91 @@ pub struct ManifestYamlOptions<'s> {
 	/// safe_key: 1
 	/// ```
 	pub quote_keys: bool,
-	/// If true - then order of fields is preserved as written,
-	/// instead of sorting alphabetically
-	#[cfg(feature = "exp-preserve-order")]
-	pub preserve_order: bool,
+	pub functions: &'s FunctionManifest,
+	/// Write string fields named `__comment_<key>__` as comments above `<key>`,
+	/// instead of manifesting them as normal fields
+	/// ```yaml
+	/// # comment
+	/// key: 1
+	/// ```
+	pub comments: bool,
+	/// Same as [`ManifestJsonOptions::escape_c1`], applies to quoted strings and keys
+	pub escape_c1: bool,
+	/// Same as [`ManifestJsonOptions::validate_field_names`]
+	pub validate_field_names: bool,
+	/// Same as [`ManifestJsonOptions::preserve_order`]
+	pub preserve_order: Option<bool>,
+	/// Write byte arrays (produced by `importbin`, `std.encodeUTF8` and `std.base64DecodeBytes`)
+	/// as base64 scalars with `!!binary` tag, instead of sequences of numbers
+	/// ```yaml
+	/// data: !!binary aGk=
+	/// ```
+	/// Only arrays which are stored as bytes are affected, equal arrays built from numbers
+	/// are still written as sequences, so this is disabled by default
+	pub binary: bool,
+}
+#[cfg(feature = "yaml")]
+impl ManifestYamlOptions<'_> {
+	fn preserve_order(&self) -> Result<bool> {
+		resolve_preserve_order(self.preserve_order)
+	}
+}
+
+/// Splits out `__comment_<key>__` fields, returning rest of fields,
+/// and names of comment fields for every commented key
+///
+/// Comments for fields which exist, but aren't manifested (hidden or skipped functions)
+/// are dropped together with their fields
+fn split_comment_fields(
+	obj: &ObjValue,
+	fields: Vec<IStr>,
+) -> Result<(Vec<IStr>, FxHashMap<IStr, IStr>)> {
+	let mut comments = FxHashMap::default();
+	let mut rest = Vec::with_capacity(fields.len());
+	for field in fields {
+		match field
+			.strip_prefix("__comment_")
+			.and_then(|f| f.strip_suffix("__"))
+		{
+			Some(key) => {
+				comments.insert(key.into(), field.clone());
+			}
+			None => rest.push(field),
+		}
+	}
+	comments.retain(|key, _| rest.contains(key) || !obj.has_field_ex(key.clone(), true));
+	for (key, comment) in &comments {
+		if !rest.contains(key) {
+			throw!(RuntimeError(
+				format!("comment field {} refers to missing field {}", comment, key).into()
+			));
+		}
+	}
+	Ok((rest, comments))
+}
+
+fn validate_field_name(field: &IStr) -> Result<()> {
+	if field.chars().any(char::is_control) {
+		throw!(InvalidFieldName(field.clone()));
+	}
+	Ok(())
+}
+
+fn comment_text(s: State, obj: &ObjValue, comment: &IStr) -> Result<IStr> {
+	match obj.get(s, comment.clone())?.expect("field exists") {
+		Val::Str(v) => Ok(v),
+		v => throw!(RuntimeError(
+			format!(
+				"comment field {} should be a string, got {}",
+				comment,
+				v.value_type()
+			)
+			.into()
+		)),
+	}
 }
 
+#[cfg(feature = "yaml")]
 /// From <https://github.com/chyh1990/yaml-rust/blob/da52a68615f2ecdd6b7e4567019f280c433c1521/src/emitter.rs#L289>
 /// With added date check
 fn yaml_needs_quotes(string: &str) -> bool {
@@ -222,21 +850,39 @@ fn yaml_needs_quotes(string: &str) -> bool {
 		|| string.parse::<f64>().is_ok()
 }
 
+/// Manifests value as YAML document, see [`ManifestYamlOptions::binary`] for byte arrays handling
+#[cfg(feature = "yaml")]
 pub fn manifest_yaml_ex(s: State, val: &Val, options: &ManifestYamlOptions<'_>) -> Result<String> {
-	let mut out = String::new();
+	let mut out = ManifestOut::new(None);
 	manifest_yaml_ex_buf(s, val, &mut out, &mut String::new(), options)?;
-	Ok(out)
+	Ok(out.buf)
 }
 
+/// Same as [`manifest_yaml_ex`], but output is written to `writer` while it is being produced.
+///
+/// On error, part of the output may already be written, and the rest is discarded,
+/// so the writer contents should be considered invalid
+#[cfg(feature = "yaml")]
+pub fn manifest_yaml_ex_to(
+	s: State,
+	val: &Val,
+	writer: &mut dyn io::Write,
+	options: &ManifestYamlOptions<'_>,
+) -> Result<()> {
+	let mut out = ManifestOut::new(Some(writer));
+	manifest_yaml_ex_buf(s, val, &mut out, &mut String::new(), options)?;
+	out.flush()
+}
+
+#[cfg(feature = "yaml")]
 #[allow(clippy::too_many_lines)]
 fn manifest_yaml_ex_buf(
 	s: State,
 	val: &Val,
-	buf: &mut String,
+	buf: &mut ManifestOut<'_>,
 	cur_padding: &mut String,
 	options: &ManifestYamlOptions<'_>,
 ) -> Result<()> {
-	use std::fmt::Write;
 	match val {
 		Val::Bool(v) => {
 			if *v {
@@ -260,14 +906,26 @@ fn manifest_yaml_ex_buf(
 			} else if !options.quote_keys && !yaml_needs_quotes(s) {
 				buf.push_str(s);
 			} else {
-				escape_string_json_buf(s, buf);
+				escape_string_json_buf(s, buf, options.escape_c1, false);
+			}
+		}
+		Val::Num(n) => {
+			warn_precision_loss(&s, *n, |buf| format_number(buf, *n));
+			format_number(buf, *n);
+		}
+		Val::Arr(ArrValue::Bytes(bytes)) if options.binary => {
+			buf.push_str("!!binary ");
+			if bytes.is_empty() {
+				buf.push_str("\"\"");
+			} else {
+				buf.push_str(&base64::encode(bytes.as_slice()));
 			}
 		}
-		Val::Num(n) => write!(buf, "{}", *n).unwrap(),
 		Val::Arr(a) => {
 			if a.is_empty() {
 				buf.push_str("[]");
 			} else {
+				buf.enter_array(a)?;
 				for (i, item) in a.iter(s.clone()).enumerate() {
 					if i != 0 {
 						buf.push('\n');
@@ -276,6 +934,7 @@ fn manifest_yaml_ex_buf(
 					let item = item?;
 					buf.push('-');
 					match &item {
+						Val::Arr(ArrValue::Bytes(_)) if options.binary => buf.push(' '),
 						Val::Arr(a) if !a.is_empty() => {
 							buf.push('\n');
 							buf.push_str(cur_padding);
@@ -284,6 +943,7 @@ fn manifest_yaml_ex_buf(
 						_ => buf.push(' '),
 					}
 					let extra_padding = match &item {
+						Val::Arr(ArrValue::Bytes(_)) if options.binary => false,
 						Val::Arr(a) => !a.is_empty(),
 						Val::Obj(o) => !o.is_empty(),
 						_ => false,
@@ -294,54 +954,973 @@ fn manifest_yaml_ex_buf(
 					}
 					manifest_yaml_ex_buf(s.clone(), &item, buf, cur_padding, options)?;
 					cur_padding.truncate(prev_len);
+					buf.flush_if_needed()?;
 				}
+				buf.exit_array();
 			}
 		}
 		Val::Obj(o) => {
-			if o.is_empty() {
+			#[cfg_attr(not(feature = "exp-preserve-order"), allow(unused_variables))]
+			let preserve_order = options.preserve_order()?;
+			let mut fields = o.fields(
+				#[cfg(feature = "exp-preserve-order")]
+				preserve_order,
+			);
+			if *options.functions == FunctionManifest::Skip {
+				fields = without_function_fields(s.clone(), o, fields)?;
+			}
+			let comments = if options.comments {
+				let (rest, comments) = split_comment_fields(o, fields)?;
+				fields = rest;
+				comments
+			} else {
+				FxHashMap::default()
+			};
+			if fields.is_empty() {
 				buf.push_str("{}");
 			} else {
-				for (i, key) in o
-					.fields(
-						#[cfg(feature = "exp-preserve-order")]
-						options.preserve_order,
-					)
-					.iter()
-					.enumerate()
-				{
+				for (i, key) in fields.iter().enumerate() {
 					if i != 0 {
 						buf.push('\n');
 						buf.push_str(cur_padding);
 					}
+					if let Some(comment) = comments.get(key) {
+						let comment = comment_text(s.clone(), o, comment)?;
+						for line in comment.split('\n') {
+							buf.push('#');
+							if !line.is_empty() {
+								buf.push(' ');
+								buf.push_str(line);
+							}
+							buf.push('\n');
+							buf.push_str(cur_padding);
+						}
+					}
+					if options.validate_field_names {
+						validate_field_name(key)?;
+					}
 					if !options.quote_keys && !yaml_needs_quotes(key) {
 						buf.push_str(key);
 					} else {
-						escape_string_json_buf(key, buf);
+						escape_string_json_buf(key, buf, options.escape_c1, false);
 					}
 					buf.push(':');
-					let prev_len = cur_padding.len();
-					let item = o.get(s.clone(), key.clone())?.expect("field exists");
-					match &item {
-						Val::Arr(a) if !a.is_empty() => {
-							buf.push('\n');
-							buf.push_str(cur_padding);
-							buf.push_str(options.arr_element_padding);
-							cur_padding.push_str(options.arr_element_padding);
+					s.push_description(
+						|| format!("field <{}> manifestification", key.clone()),
+						|| {
+							let prev_len = cur_padding.len();
+							let item = o.get(s.clone(), key.clone())?.expect("field exists");
+							match &item {
+								Val::Arr(ArrValue::Bytes(_)) if options.binary => buf.push(' '),
+								Val::Arr(a) if !a.is_empty() => {
+									buf.push('\n');
+									buf.push_str(cur_padding);
+									buf.push_str(options.arr_element_padding);
+									cur_padding.push_str(options.arr_element_padding);
+								}
+								Val::Obj(o) if !o.is_empty() => {
+									buf.push('\n');
+									buf.push_str(cur_padding);
+									buf.push_str(options.padding);
+									cur_padding.push_str(options.padding);
+								}
+								_ => buf.push(' '),
+							}
+							manifest_yaml_ex_buf(s.clone(), &item, buf, cur_padding, options)?;
+							cur_padding.truncate(prev_len);
+							Ok(Val::Null)
+						},
+					)?;
+					buf.flush_if_needed()?;
+				}
+			}
+		}
+		Val::Func(_) => match options.functions {
+			FunctionManifest::Error => throw!(RuntimeError("tried to manifest function".into())),
+			FunctionManifest::Placeholder(placeholder) => manifest_yaml_ex_buf(
+				s,
+				&Val::Str(placeholder.as_str().into()),
+				buf,
+				cur_padding,
+				options,
+			)?,
+			FunctionManifest::Skip => buf.push_str("null"),
+		},
+	}
+	Ok(())
+}
+
+pub struct ManifestTomlOptions<'s> {
+	/// Padding before contents of nested tables, and elements of multiline arrays
+	/// ```toml
+	/// [a]
+	///   b = 1
+	/// ## <- this
+	/// ```
+	pub padding: &'s str,
+	/// Write empty objects as inline tables, instead of separate sections
+	/// ```toml
+	/// a = {}
+	/// # vs
+	/// [a]
+	/// ```
+	pub inline_empty_tables: bool,
+	/// Objects with at most this many fields are written as inline tables,
+	/// instead of separate sections, `None` disables inlining of non-empty objects
+	/// ```toml
+	/// a = { b = 1 }
+	/// # vs
+	/// [a]
+	/// b = 1
+	/// ```
+	/// Arrays of objects are written as arrays of inline tables instead of `[[a]]` sections,
+	/// if every element fits
+	pub inline_table_max_fields: Option<usize>,
+	/// How deep objects may be nested in an object, which is written as inline table,
+	/// `0` means only objects without nested objects are inlined
+	pub inline_table_max_depth: usize,
+	/// Write strings holding RFC 3339 date-times, dates or times as bare TOML datetime values
+	pub datetimes: bool,
+	/// If true - then order of fields is preserved as written,
+	/// instead of sorting alphabetically
+	#[cfg(feature = "exp-preserve-order")]
+	pub preserve_order: bool,
+}
+
+fn is_toml_digits(s: &[u8]) -> bool {
+	s.iter().all(u8::is_ascii_digit)
+}
+fn is_toml_date(s: &[u8]) -> bool {
+	s.len() == 10
+		&& s[4] == b'-'
+		&& s[7] == b'-'
+		&& is_toml_digits(&s[0..4])
+		&& is_toml_digits(&s[5..7])
+		&& is_toml_digits(&s[8..10])
+}
+fn is_toml_offset(s: &[u8]) -> bool {
+	s == b"Z"
+		|| s == b"z"
+		|| (s.len() == 6
+			&& (s[0] == b'+' || s[0] == b'-')
+			&& s[3] == b':'
+			&& is_toml_digits(&s[1..3])
+			&& is_toml_digits(&s[4..6]))
+}
+fn is_toml_time(s: &[u8], offset: bool) -> bool {
+	if s.len() < 8
+		|| s[2] != b':'
+		|| s[5] != b':'
+		|| !is_toml_digits(&s[0..2])
+		|| !is_toml_digits(&s[3..5])
+		|| !is_toml_digits(&s[6..8])
+	{
+		return false;
+	}
+	let mut rest = 8;
+	if s.len() > 8 && s[8] == b'.' {
+		rest = 9;
+		while rest < s.len() && s[rest].is_ascii_digit() {
+			rest += 1;
+		}
+		// Fraction should have at least one digit
+		if rest == 9 {
+			return false;
+		}
+	}
+	rest == s.len() || (offset && is_toml_offset(&s[rest..]))
+}
+/// Is string a valid RFC 3339 date-time, date or time, which can be written as TOML datetime value
+fn is_toml_datetime(s: &str) -> bool {
+	let s = s.as_bytes();
+	is_toml_date(s)
+		|| is_toml_time(s, false)
+		|| (s.len() > 11
+			&& is_toml_date(&s[0..10])
+			&& matches!(s[10], b'T' | b't' | b' ')
+			&& is_toml_time(&s[11..], true))
+}
+
+fn escape_string_toml_buf(s: &str, buf: &mut String) {
+	use std::fmt::Write;
+	buf.push('"');
+	for c in s.chars() {
+		match c {
+			'"' => buf.push_str("\\\""),
+			'\\' => buf.push_str("\\\\"),
+			'\u{0008}' => buf.push_str("\\b"),
+			'\u{000c}' => buf.push_str("\\f"),
+			'\n' => buf.push_str("\\n"),
+			'\r' => buf.push_str("\\r"),
+			'\t' => buf.push_str("\\t"),
+			// TOML doesn't allow any control characters in basic strings, DEL included
+			c if c < 32 as char || c == 127 as char => {
+				write!(buf, "\\u{:04X}", c as u32).unwrap();
+			}
+			c => buf.push(c),
+		}
+	}
+	buf.push('"');
+}
+
+fn escape_key_toml_buf(key: &str, buf: &mut String) {
+	if !key.is_empty()
+		&& key
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+	{
+		buf.push_str(key);
+	} else {
+		escape_string_toml_buf(key, buf);
+	}
+}
+
+fn manifest_toml_value_buf(
+	s: State,
+	val: &Val,
+	buf: &mut String,
+	cur_padding: &str,
+	inline: bool,
+	options: &ManifestTomlOptions<'_>,
+) -> Result<()> {
+	use std::fmt::Write;
+	match val {
+		Val::Bool(true) => buf.push_str("true"),
+		Val::Bool(false) => buf.push_str("false"),
+		Val::Null => throw!(RuntimeError("tried to manifest null as toml".into())),
+		Val::Str(s) if options.datetimes && is_toml_datetime(s) => buf.push_str(s),
+		Val::Str(s) => escape_string_toml_buf(s, buf),
+		Val::Num(n) if n.is_nan() => buf.push_str("nan"),
+		Val::Num(n) if n.is_infinite() => buf.push_str(if *n > 0.0 { "inf" } else { "-inf" }),
+		Val::Num(n) => {
+			// Whole numbers are written as integers, which are limited to i64 in TOML,
+			// bigger numbers (always whole in f64) are written as floats
+			let write = |buf: &mut String| {
+				if n.abs() >= i64::MAX as f64 {
+					write!(buf, "{:e}", n).unwrap();
+				} else {
+					write!(buf, "{}", n).unwrap();
+				}
+			};
+			warn_precision_loss(&s, *n, write);
+			write(buf);
+		}
+		Val::Arr(items) => {
+			if items.is_empty() {
+				buf.push_str("[]");
+				return Ok(());
+			}
+			let mut item_padding = cur_padding.to_owned();
+			item_padding.push_str(options.padding);
+			buf.push('[');
+			for (i, item) in items.iter(s.clone()).enumerate() {
+				if i != 0 {
+					buf.push(',');
+				}
+				if inline {
+					buf.push(' ');
+				} else {
+					buf.push('\n');
+					buf.push_str(&item_padding);
+				}
+				s.push_description(
+					|| format!("elem <{}> manifestification", i),
+					|| manifest_toml_value_buf(s.clone(), &item?, buf, "", true, options),
+				)?;
+			}
+			if inline {
+				buf.push(' ');
+			} else {
+				buf.push('\n');
+				buf.push_str(cur_padding);
+			}
+			buf.push(']');
+		}
+		Val::Obj(obj) => {
+			obj.run_assertions(s.clone())?;
+			let fields = obj.fields(
+				#[cfg(feature = "exp-preserve-order")]
+				options.preserve_order,
+			);
+			if fields.is_empty() {
+				buf.push_str("{}");
+				return Ok(());
+			}
+			buf.push_str("{ ");
+			for (i, field) in fields.into_iter().enumerate() {
+				if i != 0 {
+					buf.push_str(", ");
+				}
+				escape_key_toml_buf(&field, buf);
+				buf.push_str(" = ");
+				s.push_description(
+					|| format!("field <{}> manifestification", field.clone()),
+					|| {
+						let value = obj.get(s.clone(), field.clone())?.expect("field exists");
+						manifest_toml_value_buf(s.clone(), &value, buf, "", true, options)
+					},
+				)?;
+			}
+			buf.push_str(" }");
+		}
+		Val::Func(_) => throw!(RuntimeError("tried to manifest function".into())),
+	}
+	Ok(())
+}
+
+/// Can object be written as inline table, according to `inline_table_max_*` options
+fn toml_fits_inline(s: State, obj: &ObjValue, max_fields: usize, max_depth: usize) -> Result<bool> {
+	if obj.len() > max_fields {
+		return Ok(false);
+	}
+	for field in obj.fields(
+		#[cfg(feature = "exp-preserve-order")]
+		false,
+	) {
+		let value = obj.get(s.clone(), field)?.expect("field exists");
+		if !toml_value_fits_inline(s.clone(), &value, max_fields, max_depth)? {
+			return Ok(false);
+		}
+	}
+	Ok(true)
+}
+fn toml_value_fits_inline(
+	s: State,
+	value: &Val,
+	max_fields: usize,
+	max_depth: usize,
+) -> Result<bool> {
+	Ok(match value {
+		Val::Obj(obj) => max_depth != 0 && toml_fits_inline(s, obj, max_fields, max_depth - 1)?,
+		Val::Arr(items) => {
+			for item in items.iter(s.clone()) {
+				if !toml_value_fits_inline(s.clone(), &item?, max_fields, max_depth)? {
+					return Ok(false);
+				}
+			}
+			true
+		}
+		_ => true,
+	})
+}
+
+fn manifest_toml_table_buf(
+	s: State,
+	obj: &ObjValue,
+	buf: &mut String,
+	path: &mut Vec<IStr>,
+	cur_padding: &str,
+	options: &ManifestTomlOptions<'_>,
+) -> Result<()> {
+	obj.run_assertions(s.clone())?;
+	let fields = obj.fields(
+		#[cfg(feature = "exp-preserve-order")]
+		options.preserve_order,
+	);
+	let mut values = Vec::with_capacity(fields.len());
+	for field in fields {
+		let (value, is_section) = s.push_description(
+			|| format!("field <{}> manifestification", field.clone()),
+			|| {
+				let value = obj.get(s.clone(), field.clone())?.expect("field exists");
+				let is_section = match &value {
+					Val::Obj(o) if o.is_empty() => !options.inline_empty_tables,
+					Val::Obj(o) => match options.inline_table_max_fields {
+						Some(max_fields) => !toml_fits_inline(
+							s.clone(),
+							o,
+							max_fields,
+							options.inline_table_max_depth,
+						)?,
+						None => true,
+					},
+					// Only non-empty arrays consisting of objects are written as arrays of tables
+					Val::Arr(items) if !items.is_empty() => {
+						let mut all_objects = true;
+						for item in items.iter(s.clone()) {
+							if !matches!(item?, Val::Obj(_)) {
+								all_objects = false;
+								break;
+							}
 						}
-						Val::Obj(o) if !o.is_empty() => {
-							buf.push('\n');
-							buf.push_str(cur_padding);
-							buf.push_str(options.padding);
-							cur_padding.push_str(options.padding);
+						all_objects
+							&& match options.inline_table_max_fields {
+								// Elements are inline tables themselves, not nested in one
+								Some(max_fields) => !toml_value_fits_inline(
+									s.clone(),
+									&value,
+									max_fields,
+									options.inline_table_max_depth + 1,
+								)?,
+								None => true,
+							}
+					}
+					_ => false,
+				};
+				Ok((value, is_section))
+			},
+		)?;
+		values.push((field, value, is_section));
+	}
+
+	let mut first = true;
+	// Plain key-value pairs should go before any section, as otherwise they will belong to it
+	for (field, value, _) in values.iter().filter(|(_, _, section)| !section) {
+		if !first {
+			buf.push('\n');
+		}
+		first = false;
+		buf.push_str(cur_padding);
+		escape_key_toml_buf(field, buf);
+		buf.push_str(" = ");
+		s.push_description(
+			|| format!("field <{}> manifestification", field.clone()),
+			|| manifest_toml_value_buf(s.clone(), value, buf, cur_padding, false, options),
+		)?;
+	}
+	let mut nested_padding = cur_padding.to_owned();
+	nested_padding.push_str(options.padding);
+	for (field, value, _) in values.iter().filter(|(_, _, section)| *section) {
+		if !first {
+			buf.push_str("\n\n");
+		}
+		first = false;
+		path.push(field.clone());
+		s.push_description(
+			|| format!("field <{}> manifestification", field.clone()),
+			|| {
+				let items = match value {
+					Val::Obj(obj) => vec![(obj.clone(), None)],
+					Val::Arr(items) => {
+						let mut out = Vec::with_capacity(items.len());
+						for (i, item) in items.iter(s.clone()).enumerate() {
+							match item? {
+								Val::Obj(obj) => out.push((obj, Some(i))),
+								_ => unreachable!("checked when collecting fields"),
+							}
 						}
-						_ => buf.push(' '),
+						out
+					}
+					_ => unreachable!("checked when collecting fields"),
+				};
+				for (i, (obj, index)) in items.into_iter().enumerate() {
+					if i != 0 {
+						buf.push_str("\n\n");
+					}
+					let (open, close) = if index.is_some() {
+						("[[", "]]")
+					} else {
+						("[", "]")
+					};
+					buf.push_str(cur_padding);
+					buf.push_str(open);
+					for (i, key) in path.iter().enumerate() {
+						if i != 0 {
+							buf.push('.');
+						}
+						escape_key_toml_buf(key, buf);
+					}
+					buf.push_str(close);
+					if obj.is_empty() {
+						continue;
+					}
+					buf.push('\n');
+					if let Some(index) = index {
+						s.push_description(
+							|| format!("elem <{}> manifestification", index),
+							|| {
+								manifest_toml_table_buf(
+									s.clone(),
+									&obj,
+									buf,
+									path,
+									&nested_padding,
+									options,
+								)
+							},
+						)?;
+					} else {
+						manifest_toml_table_buf(
+							s.clone(),
+							&obj,
+							buf,
+							path,
+							&nested_padding,
+							options,
+						)?;
 					}
-					manifest_yaml_ex_buf(s.clone(), &item, buf, cur_padding, options)?;
-					cur_padding.truncate(prev_len);
 				}
+				Ok(())
+			},
+		)?;
+		path.pop();
+	}
+	Ok(())
+}
+
+pub fn manifest_toml_ex(s: State, val: &Val, options: &ManifestTomlOptions<'_>) -> Result<String> {
+	let obj = match val {
+		Val::Obj(obj) => obj,
+		_ => throw!(RuntimeError(
+			format!(
+				"toml manifest output should be an object, got {}",
+				val.value_type()
+			)
+			.into()
+		)),
+	};
+	let mut out = String::new();
+	manifest_toml_table_buf(s, obj, &mut out, &mut Vec::new(), "", options)?;
+	Ok(out)
+}
+
+/// Options of `JsonML` to xml manifestification
+pub struct ManifestXmlOptions<'s> {
+	/// Write elements without children as `<tag/>` instead of `<tag></tag>`
+	pub self_closing: bool,
+	/// Put children of elements, consisting only of other elements, on their own lines,
+	/// indented with this string
+	pub indent: Option<&'s str>,
+	#[cfg(feature = "exp-preserve-order")]
+	pub preserve_order: bool,
+}
+
+fn escape_xml_buf(s: &str, attr: bool, buf: &mut String) {
+	for c in s.chars() {
+		match c {
+			'&' => buf.push_str("&amp;"),
+			'<' => buf.push_str("&lt;"),
+			'>' => buf.push_str("&gt;"),
+			'"' if attr => buf.push_str("&quot;"),
+			c => buf.push(c),
+		}
+	}
+}
+
+fn manifest_xml_jsonml_buf(
+	s: State,
+	elem: &ArrValue,
+	buf: &mut String,
+	cur_indent: &mut String,
+	options: &ManifestXmlOptions<'_>,
+) -> Result<()> {
+	let tag = match elem.get(s.clone(), 0)? {
+		Some(Val::Str(tag)) => tag,
+		Some(v) => throw!(RuntimeError(
+			format!("JsonML tag should be a string, got {}", v.value_type()).into()
+		)),
+		None => throw!(RuntimeError("JsonML element should have a tag".into())),
+	};
+	let attrs = match elem.get(s.clone(), 1)? {
+		Some(Val::Obj(attrs)) => Some(attrs),
+		_ => None,
+	};
+
+	buf.push('<');
+	buf.push_str(&tag);
+	if let Some(attrs) = &attrs {
+		for key in attrs.fields(
+			#[cfg(feature = "exp-preserve-order")]
+			options.preserve_order,
+		) {
+			let value = attrs.get(s.clone(), key.clone())?.expect("field exists");
+			let value = match value {
+				Val::Str(v) => v,
+				Val::Num(_) | Val::Bool(_) => value.to_string(s.clone())?,
+				v => throw!(RuntimeError(
+					format!(
+						"attribute {} of <{}> should be a string, number or boolean, got {}",
+						key,
+						tag,
+						v.value_type()
+					)
+					.into()
+				)),
+			};
+			buf.push(' ');
+			buf.push_str(&key);
+			buf.push_str("=\"");
+			escape_xml_buf(&value, true, buf);
+			buf.push('"');
+		}
+	}
+
+	let children_start = if attrs.is_some() { 2 } else { 1 };
+	let children = elem
+		.iter(s.clone())
+		.skip(children_start)
+		.collect::<Result<Vec<_>>>()?;
+	if children.is_empty() && options.self_closing {
+		buf.push_str("/>");
+		return Ok(());
+	}
+	buf.push('>');
+
+	let pretty = options
+		.indent
+		.filter(|_| !children.is_empty() && children.iter().all(|c| matches!(c, Val::Arr(_))));
+	let prev_len = cur_indent.len();
+	if let Some(indent) = pretty {
+		cur_indent.push_str(indent);
+	}
+	for child in &children {
+		if pretty.is_some() {
+			buf.push('\n');
+			buf.push_str(cur_indent);
+		}
+		match child {
+			Val::Str(text) => escape_xml_buf(text, false, buf),
+			Val::Arr(child) => manifest_xml_jsonml_buf(s.clone(), child, buf, cur_indent, options)?,
+			v => throw!(RuntimeError(
+				format!(
+					"children of <{}> should be strings or JsonML arrays, got {}",
+					tag,
+					v.value_type()
+				)
+				.into()
+			)),
+		}
+	}
+	cur_indent.truncate(prev_len);
+	if pretty.is_some() {
+		buf.push('\n');
+		buf.push_str(cur_indent);
+	}
+
+	buf.push_str("</");
+	buf.push_str(&tag);
+	buf.push('>');
+	Ok(())
+}
+
+/// Manifests `JsonML` value (`[tag, attrs?, children...]`) as xml, escaping text and attribute values
+pub fn manifest_xml_jsonml(
+	s: State,
+	val: &Val,
+	options: &ManifestXmlOptions<'_>,
+) -> Result<String> {
+	let elem = match val {
+		Val::Arr(elem) => elem,
+		_ => throw!(RuntimeError(
+			format!(
+				"Expected a JSONML value (an array), got {}",
+				val.value_type()
+			)
+			.into()
+		)),
+	};
+	let mut out = String::new();
+	manifest_xml_jsonml_buf(s, elem, &mut out, &mut String::new(), options)?;
+	Ok(out)
+}
+
+/// Options of `std.manifestIni`
+pub struct ManifestIniOptions<'s> {
+	/// Written in place of `true` values
+	pub true_value: &'s str,
+	/// Written in place of `false` values
+	pub false_value: &'s str,
+	/// Write integral numbers without decimal point, i.e `1` instead of `1.0`
+	pub drop_integral_decimal: bool,
+	#[cfg(feature = "exp-preserve-order")]
+	pub preserve_order: bool,
+}
+
+fn manifest_ini_value_buf(
+	s: State,
+	value: Val,
+	buf: &mut String,
+	options: &ManifestIniOptions<'_>,
+) -> Result<()> {
+	match value {
+		Val::Str(v) => buf.push_str(&v),
+		Val::Bool(true) => buf.push_str(options.true_value),
+		Val::Bool(false) => buf.push_str(options.false_value),
+		Val::Num(_) => {
+			let n = value.to_string(s)?;
+			buf.push_str(&n);
+			if !options.drop_integral_decimal && !n.contains(&['.', 'e', 'E'][..]) {
+				buf.push_str(".0");
 			}
 		}
-		Val::Func(_) => throw!(RuntimeError("tried to manifest function".into())),
+		_ => buf.push_str(&value.to_string(s)?),
 	}
 	Ok(())
 }
+
+fn manifest_ini_body_buf(
+	s: State,
+	body: &ObjValue,
+	buf: &mut String,
+	options: &ManifestIniOptions<'_>,
+) -> Result<()> {
+	for key in body.fields(
+		#[cfg(feature = "exp-preserve-order")]
+		options.preserve_order,
+	) {
+		let value = body.get(s.clone(), key.clone())?.expect("field exists");
+		let values = match value {
+			Val::Arr(values) => values.iter(s.clone()).collect::<Result<Vec<_>>>()?,
+			value => vec![value],
+		};
+		for value in values {
+			buf.push_str(&key);
+			buf.push_str(" = ");
+			manifest_ini_value_buf(s.clone(), value, buf, options)?;
+			buf.push('\n');
+		}
+	}
+	Ok(())
+}
+
+fn expect_ini_body(value: Val, name: &str) -> Result<ObjValue> {
+	match value {
+		Val::Obj(body) => Ok(body),
+		v => throw!(RuntimeError(
+			format!("ini {} should be an object, got {}", name, v.value_type()).into()
+		)),
+	}
+}
+
+/// Manifests object with optional `main` and required `sections` fields as INI,
+/// fields holding arrays are written once per element
+pub fn manifest_ini(s: State, ini: &ObjValue, options: &ManifestIniOptions<'_>) -> Result<String> {
+	let mut out = String::new();
+	if ini.has_field("main".into()) {
+		let main = ini.get(s.clone(), "main".into())?.expect("field exists");
+		let main = expect_ini_body(main, "main")?;
+		manifest_ini_body_buf(s.clone(), &main, &mut out, options)?;
+	}
+	let sections = match ini.get(s.clone(), "sections".into())? {
+		Some(sections) => expect_ini_body(sections, "sections")?,
+		None => throw!(NoSuchField("sections".into(), vec![])),
+	};
+	for name in sections.fields(
+		#[cfg(feature = "exp-preserve-order")]
+		options.preserve_order,
+	) {
+		let section = sections
+			.get(s.clone(), name.clone())?
+			.expect("field exists");
+		let section = expect_ini_body(section, &format!("section {}", name))?;
+		out.push('[');
+		out.push_str(&name);
+		out.push_str("]\n");
+		manifest_ini_body_buf(s.clone(), &section, &mut out, options)?;
+	}
+	Ok(out)
+}
+
+/// Options of CSV manifestification
+pub struct ManifestCsvOptions<'s> {
+	/// Columns of the output, in order. If not set, fields of the first row are used
+	pub headers: Option<&'s [IStr]>,
+	#[cfg(feature = "exp-preserve-order")]
+	pub preserve_order: bool,
+}
+
+fn escape_csv_buf(s: &str, buf: &mut String) {
+	if !s.contains(&[',', '"', '\n', '\r'][..]) {
+		buf.push_str(s);
+		return;
+	}
+	buf.push('"');
+	for c in s.chars() {
+		if c == '"' {
+			buf.push('"');
+		}
+		buf.push(c);
+	}
+	buf.push('"');
+}
+
+fn write_csv_row<'a>(row: impl IntoIterator<Item = &'a str>, buf: &mut String) {
+	for (i, cell) in row.into_iter().enumerate() {
+		if i != 0 {
+			buf.push(',');
+		}
+		escape_csv_buf(cell, buf);
+	}
+	buf.push('\n');
+}
+
+/// Manifests array of flat objects as CSV, one row per object, preceded by header row.
+///
+/// Strings are quoted per RFC 4180 when needed, `null` and missing fields are written as empty cells,
+/// rows are terminated with `\n`
+pub fn manifest_csv(s: State, val: &Val, options: &ManifestCsvOptions<'_>) -> Result<String> {
+	let rows = match val {
+		Val::Arr(rows) => rows,
+		_ => throw!(RuntimeError(
+			format!("CSV output should be an array, got {}", val.value_type()).into()
+		)),
+	};
+	let mut objs = Vec::with_capacity(rows.len());
+	for (i, row) in rows.iter(s.clone()).enumerate() {
+		match row? {
+			Val::Obj(row) => objs.push(row),
+			v => throw!(RuntimeError(
+				format!("CSV row {} should be an object, got {}", i, v.value_type()).into()
+			)),
+		}
+	}
+
+	let headers = match options.headers {
+		Some(headers) => headers.to_vec(),
+		None => match objs.first() {
+			Some(first) => first.fields(
+				#[cfg(feature = "exp-preserve-order")]
+				options.preserve_order,
+			),
+			None => return Ok(String::new()),
+		},
+	};
+
+	let mut out = String::new();
+	write_csv_row(headers.iter().map(IStr::as_str), &mut out);
+	let mut cells = Vec::with_capacity(headers.len());
+	for (i, row) in objs.iter().enumerate() {
+		cells.clear();
+		for header in &headers {
+			let cell = match row.get(s.clone(), header.clone())? {
+				None | Some(Val::Null) => IStr::from(""),
+				Some(Val::Str(v)) => v,
+				Some(v @ (Val::Num(_) | Val::Bool(_))) => v.to_string(s.clone())?,
+				Some(v) => throw!(RuntimeError(
+					format!(
+						"field {} of CSV row {} should be a primitive, got {}",
+						header,
+						i,
+						v.value_type()
+					)
+					.into()
+				)),
+			};
+			cells.push(cell);
+		}
+		write_csv_row(cells.iter().map(IStr::as_str), &mut out);
+	}
+	Ok(out)
+}
+
+#[cfg(test)]
+mod tests_precision {
+	use super::precision_loss_warning;
+
+	#[test]
+	fn precision_loss() {
+		assert_eq!(precision_loss_warning(0.1, "0.1"), None);
+		assert_eq!(precision_loss_warning(-0.0, "-0"), None);
+		assert_eq!(
+			precision_loss_warning(0.1, "0.10000001").as_deref(),
+			Some("WARNING: number 0.1 is manifested as 0.10000001, which is parsed back as a different value")
+		);
+		assert_eq!(
+			precision_loss_warning(1e20, "100000000000000000000").as_deref(),
+			Some(
+				"WARNING: number 100000000000000000000 exceeds 2^53, integer precision may be lost"
+			)
+		);
+	}
+}
+
+#[cfg(test)]
+pub mod tests_order {
+	use super::*;
+
+	fn json_order(preserve_order: Option<bool>) -> Result<String> {
+		let s = State::default();
+		s.with_stdlib();
+		let v = s
+			.evaluate_snippet("snip".to_owned(), "{b: 1, a: {d: 2, c: 3}}".into())
+			.unwrap();
+		manifest_json_ex(
+			s,
+			&v,
+			&ManifestJsonOptions {
+				padding: "",
+				mtype: ManifestType::Minify,
+				newline: "",
+				key_val_sep: ":",
+				preserve_order,
+				..ManifestJsonOptions::default()
+			},
+		)
+	}
+
+	#[test]
+	fn json_sorted_override() {
+		assert_eq!(
+			json_order(Some(false)).unwrap(),
+			r#"{"a":{"c":3,"d":2},"b":1}"#
+		);
+	}
+
+	#[test]
+	fn json_default_order() {
+		assert_eq!(json_order(None).unwrap(), r#"{"a":{"c":3,"d":2},"b":1}"#);
+	}
+
+	#[test]
+	fn json_preserved_order() {
+		#[cfg(feature = "exp-preserve-order")]
+		assert_eq!(
+			json_order(Some(true)).unwrap(),
+			r#"{"b":1,"a":{"d":2,"c":3}}"#
+		);
+		#[cfg(not(feature = "exp-preserve-order"))]
+		assert!(json_order(Some(true)).is_err());
+	}
+}
+
+#[cfg(test)]
+mod tests_field_names {
+	use super::*;
+
+	#[test]
+	fn control_field_names() {
+		let s = State::default();
+		s.with_stdlib();
+		let v = s
+			.evaluate_snippet("snip".to_owned(), "{['a\\u007f']: 1}".into())
+			.unwrap();
+		let json = |validate_field_names| {
+			manifest_json_ex(
+				s.clone(),
+				&v,
+				&ManifestJsonOptions {
+					validate_field_names,
+					..ManifestJsonOptions::default()
+				},
+			)
+		};
+		assert_eq!(json(false).unwrap(), "{\n    \"a\\u007f\": 1\n}");
+		assert_eq!(
+			json(true).unwrap_err().error().to_string(),
+			"invalid field name: \"a\\u007f\""
+		);
+
+		#[cfg(feature = "yaml")]
+		{
+			let yaml = |validate_field_names| {
+				manifest_yaml_ex(
+					s.clone(),
+					&v,
+					&ManifestYamlOptions {
+						padding: "  ",
+						arr_element_padding: "  ",
+						quote_keys: true,
+						functions: &FunctionManifest::Error,
+						comments: false,
+						escape_c1: true,
+						validate_field_names,
+						preserve_order: None,
+						binary: false,
+					},
+				)
+			};
+			assert_eq!(yaml(false).unwrap(), "\"a\\u007f\": 1");
+			assert!(yaml(true).is_err());
+		}
+	}
+}
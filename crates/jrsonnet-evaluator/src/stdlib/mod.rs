@@ -4,27 +4,36 @@
 use std::collections::HashMap;
 
 use format::{format_arr, format_obj};
-use jrsonnet_gcmodule::Cc;
+use jrsonnet_gcmodule::{Cc, Trace};
 use jrsonnet_interner::{IBytes, IStr};
+use rustc_hash::FxHashMap;
+#[cfg(feature = "yaml")]
 use serde::Deserialize;
+#[cfg(feature = "yaml")]
 use serde_yaml_with_quirks::DeserializingQuirks;
 
 use crate::{
 	error::{Error::*, Result},
 	function::{builtin::StaticBuiltin, ArgLike, CallLocation, FuncVal},
 	operator::evaluate_mod_op,
-	stdlib::manifest::{manifest_yaml_ex, ManifestYamlOptions},
-	throw,
-	typed::{Any, BoundedUsize, Either2, Either4, PositiveF64, Typed, VecVal, M1},
-	val::{equals, primitive_equals, ArrValue, IndexableVal, Slice},
-	Either, ObjValue, State, Val,
+	tb, throw,
+	typed::{
+		self, Any, BoundedUsize, CheckType, Either2, Either4, Null, PositiveF64, Typed, ValType,
+		VecVal, M1,
+	},
+	val::{equals, primitive_equals, whole_number, ArrValue, IndexableVal, ThunkValue},
+	Either, LazyBinding, ObjValue, ObjValueBuilder, State, Thunk, Val,
 };
 
 pub mod expr;
 pub use expr::*;
 
-use self::manifest::{escape_string_json, manifest_json_ex, ManifestJsonOptions, ManifestType};
+use self::manifest::{
+	escape_string_json_with, manifest_json_ex, FunctionManifest, ManifestJsonOptions, NumberFormat,
+};
 
+#[cfg(feature = "dates")]
+mod dates;
 pub mod format;
 pub mod manifest;
 pub mod sort;
@@ -77,12 +86,11 @@ pub fn std_slice(
 				return Ok(Val::Arr(ArrValue::new_eager()));
 			}
 
-			Ok(Val::Arr(ArrValue::Slice(Box::new(Slice {
-				inner: arr.clone(),
-				from: index as u32,
-				to: end as u32,
-				step: step as u32,
-			}))))
+			Ok(Val::Arr(arr.clone().slice(
+				Some(index),
+				Some(end),
+				Some(step),
+			)))
 		}
 	}
 }
@@ -91,13 +99,25 @@ type BuiltinsType = HashMap<IStr, &'static dyn StaticBuiltin>;
 
 thread_local! {
 	pub static BUILTINS: BuiltinsType = {
-		[
+		#[cfg_attr(
+			not(any(feature = "yaml", feature = "hashes", feature = "dates")),
+			allow(unused_mut)
+		)]
+		let mut builtins: BuiltinsType = [
 			("length".into(), builtin_length::INST),
 			("type".into(), builtin_type::INST),
+			("assertType".into(), builtin_assert_type::INST),
 			("makeArray".into(), builtin_make_array::INST),
 			("codepoint".into(), builtin_codepoint::INST),
 			("objectFieldsEx".into(), builtin_object_fields_ex::INST),
+			("objectFieldsSplit".into(), builtin_object_fields_split::INST),
 			("objectHasEx".into(), builtin_object_has_ex::INST),
+			("objectHasPath".into(), builtin_object_has_path::INST),
+			("getPath".into(), builtin_get_path::INST),
+			("mergeField".into(), builtin_merge_field::INST),
+			("fromPairs".into(), builtin_from_pairs::INST),
+			("toPairs".into(), builtin_to_pairs::INST),
+			("objectKeysValuesEx".into(), builtin_object_keys_values_ex::INST),
 			("slice".into(), builtin_slice::INST),
 			("substr".into(), builtin_substr::INST),
 			("primitiveEquals".into(), builtin_primitive_equals::INST),
@@ -106,6 +126,10 @@ thread_local! {
 			("mod".into(), builtin_mod::INST),
 			("floor".into(), builtin_floor::INST),
 			("ceil".into(), builtin_ceil::INST),
+			("round".into(), builtin_round::INST),
+			("trunc".into(), builtin_trunc::INST),
+			("sign".into(), builtin_sign::INST),
+			("clamp".into(), builtin_clamp::INST),
 			("log".into(), builtin_log::INST),
 			("pow".into(), builtin_pow::INST),
 			("sqrt".into(), builtin_sqrt::INST),
@@ -120,12 +144,16 @@ thread_local! {
 			("exponent".into(), builtin_exponent::INST),
 			("extVar".into(), builtin_ext_var::INST),
 			("native".into(), builtin_native::INST),
+			("nativeNames".into(), builtin_native_names::INST),
 			("filter".into(), builtin_filter::INST),
+			("filterWithProgress".into(), builtin_filter_with_progress::INST),
 			("map".into(), builtin_map::INST),
 			("flatMap".into(), builtin_flatmap::INST),
 			("foldl".into(), builtin_foldl::INST),
 			("foldr".into(), builtin_foldr::INST),
+			("reduceWhile".into(), builtin_reduce_while::INST),
 			("sort".into(), builtin_sort::INST),
+			("set".into(), builtin_set::INST),
 			("format".into(), builtin_format::INST),
 			("range".into(), builtin_range::INST),
 			("char".into(), builtin_char::INST),
@@ -139,19 +167,46 @@ thread_local! {
 			("join".into(), builtin_join::INST),
 			("escapeStringJson".into(), builtin_escape_string_json::INST),
 			("manifestJsonEx".into(), builtin_manifest_json_ex::INST),
-			("manifestYamlDoc".into(), builtin_manifest_yaml_doc::INST),
+			("manifestJsonBytes".into(), builtin_manifest_json_bytes::INST),
+			("manifestJsonl".into(), builtin_manifest_jsonl::INST),
+			("manifestTomlEx".into(), builtin_manifest_toml_ex::INST),
+			("manifestIni".into(), builtin_manifest_ini::INST),
+			("manifestXmlJsonml".into(), builtin_manifest_xml_jsonml::INST),
 			("reverse".into(), builtin_reverse::INST),
 			("strReplace".into(), builtin_str_replace::INST),
 			("splitLimit".into(), builtin_splitlimit::INST),
 			("parseJson".into(), builtin_parse_json::INST),
-			("parseYaml".into(), builtin_parse_yaml::INST),
+			("parseInt".into(), builtin_parse_int::INST),
+			("parseIntAuto".into(), builtin_parse_int_auto::INST),
 			("asciiUpper".into(), builtin_ascii_upper::INST),
 			("asciiLower".into(), builtin_ascii_lower::INST),
+			("basename".into(), builtin_basename::INST),
+			("dirname".into(), builtin_dirname::INST),
+			("joinPath".into(), builtin_join_path::INST),
 			("member".into(), builtin_member::INST),
 			("count".into(), builtin_count::INST),
 			("any".into(), builtin_any::INST),
 			("all".into(), builtin_all::INST),
-		].iter().cloned().collect()
+		].iter().cloned().collect();
+		#[cfg(feature = "yaml")]
+		builtins.extend([
+			("manifestYamlDoc".into(), builtin_manifest_yaml_doc::INST),
+			("parseYaml".into(), builtin_parse_yaml::INST),
+		]);
+		#[cfg(feature = "hashes")]
+		builtins.extend([
+			("sha1".into(), builtin_sha1::INST),
+			("sha256".into(), builtin_sha256::INST),
+			("sha512".into(), builtin_sha512::INST),
+		]);
+		#[cfg(feature = "dates")]
+		builtins.extend([
+			("dateNow".into(), dates::builtin_date_now::INST),
+			("dateParse".into(), dates::builtin_date_parse::INST),
+			("dateFormat".into(), dates::builtin_date_format::INST),
+			("dateAdd".into(), dates::builtin_date_add::INST),
+		]);
+		builtins
 	};
 }
 
@@ -159,7 +214,7 @@ thread_local! {
 fn builtin_length(x: Either![IStr, ArrValue, ObjValue, FuncVal]) -> Result<usize> {
 	use Either4::*;
 	Ok(match x {
-		A(x) => x.chars().count(),
+		A(x) => x.char_len(),
 		B(x) => x.len(),
 		C(x) => x.len(),
 		D(f) => f.params_len(),
@@ -171,13 +226,76 @@ fn builtin_type(x: Any) -> Result<IStr> {
 	Ok(x.0.value_type().name().into())
 }
 
+/// Checks `value` against `type_spec`, returning `value` itself if it matches.
+///
+/// `type_spec` is either an object, mapping required field names to specs of their values
+/// (other fields are not checked), or a string:
+/// ```text
+/// ty    = ty " | " ty     value matches any of types
+///       / ty " & " ty     value matches every type, binds tighter than "|"
+///       / "(" ty ")"
+///       / "any" / "boolean" / "null" / "string" / "number" / "array" / "object" / "function"
+///       / "character"     string of a single char
+///       / "Array<" ty ">" array, every element of which matches ty
+///       / "BoundedNumber<" int ", " int ">"  number in inclusive range
+/// ```
+/// Whitespace is only allowed where shown
 #[jrsonnet_macros::builtin]
-fn builtin_make_array(s: State, sz: usize, func: FuncVal) -> Result<VecVal> {
-	let mut out = Vec::with_capacity(sz);
-	for i in 0..sz {
-		out.push(func.evaluate_simple(s.clone(), &(i as f64,))?);
+fn builtin_assert_type(s: State, value: Any, type_spec: Any) -> Result<Any> {
+	check_type_spec(s, &value.0, &type_spec.0)?;
+	Ok(value)
+}
+
+fn check_type_spec(s: State, value: &Val, spec: &Val) -> Result<()> {
+	match spec {
+		Val::Str(spec) => {
+			let ty = jrsonnet_types::parser::ty(spec).map_err(|e| {
+				RuntimeError(format!("invalid type spec {:?}: {}", spec.as_str(), e).into())
+			})?;
+			ty.check(s, value)
+		}
+		Val::Obj(spec) => {
+			let obj = match value {
+				Val::Obj(obj) => obj,
+				v => throw!(typed::TypeError::ExpectedGot(
+					ValType::Obj.into(),
+					v.value_type()
+				)),
+			};
+			for field in spec.fields(
+				#[cfg(feature = "exp-preserve-order")]
+				false,
+			) {
+				let field_spec = spec.get(s.clone(), field.clone())?.expect("field exists");
+				let field_value = match obj.get(s.clone(), field.clone())? {
+					Some(v) => v,
+					None => throw!(typed::TypeError::MissingProperty(
+						field.as_str().into(),
+						ValType::Obj.into()
+					)),
+				};
+				typed::push_type_description(
+					s.clone(),
+					|| format!("property {}", field),
+					|| typed::ValuePathItem::Field(field.as_str().into()),
+					|| check_type_spec(s.clone(), &field_value, &field_spec),
+				)?;
+			}
+			Ok(())
+		}
+		v => throw!(RuntimeError(
+			format!(
+				"type spec should be a string or an object, got {}",
+				v.value_type()
+			)
+			.into()
+		)),
 	}
-	Ok(VecVal(Cc::new(out)))
+}
+
+#[jrsonnet_macros::builtin]
+fn builtin_make_array(sz: usize, func: FuncVal) -> Result<ArrValue> {
+	Ok(ArrValue::new_computed(sz, func))
 }
 
 #[jrsonnet_macros::builtin]
@@ -203,11 +321,233 @@ fn builtin_object_fields_ex(
 	)))
 }
 
+#[jrsonnet_macros::builtin]
+fn builtin_object_fields_split(
+	s: State,
+	obj: ObjValue,
+	#[cfg(feature = "exp-preserve-order")] preserve_order: Option<bool>,
+) -> Result<ObjValue> {
+	#[cfg(feature = "exp-preserve-order")]
+	let preserve_order = preserve_order.unwrap_or(false);
+	let (visible, hidden) = obj.fields_split(
+		#[cfg(feature = "exp-preserve-order")]
+		preserve_order,
+	);
+	let mut out = ObjValueBuilder::with_capacity(2);
+	out.member("visible".into()).value(
+		s.clone(),
+		Val::Arr(visible.into_iter().map(Val::Str).collect::<Vec<_>>().into()),
+	)?;
+	out.member("hidden".into()).value(
+		s,
+		Val::Arr(hidden.into_iter().map(Val::Str).collect::<Vec<_>>().into()),
+	)?;
+	Ok(out.build())
+}
+
 #[jrsonnet_macros::builtin]
 fn builtin_object_has_ex(obj: ObjValue, f: IStr, inc_hidden: bool) -> Result<bool> {
 	Ok(obj.has_field_ex(f, inc_hidden))
 }
 
+/// Walks nested objects by field names and arrays by indexes,
+/// returns `None` if some segment is missing, or value has unexpected type for it
+fn walk_path(s: State, value: Val, path: &ArrValue, inc_hidden: bool) -> Result<Option<Val>> {
+	let mut cur = value;
+	for (i, segment) in path.iter(s.clone()).enumerate() {
+		let next = match (&cur, segment?) {
+			(Val::Obj(obj), Val::Str(field)) => {
+				if !obj.has_field_ex(field.clone(), inc_hidden) {
+					return Ok(None);
+				}
+				obj.get(s.clone(), field)?
+			}
+			(Val::Arr(arr), Val::Num(index)) => match whole_number(index) {
+				Some(index) if index >= 0.0 => arr.get(s.clone(), index as usize)?,
+				_ => None,
+			},
+			(_, Val::Str(_) | Val::Num(_)) => None,
+			(_, segment) => throw!(RuntimeError(
+				format!(
+					"path segment #{} should be a string or a number, got {}",
+					i,
+					segment.value_type()
+				)
+				.into()
+			)),
+		};
+		match next {
+			Some(next) => cur = next,
+			None => return Ok(None),
+		}
+	}
+	Ok(Some(cur))
+}
+
+#[jrsonnet_macros::builtin]
+fn builtin_object_has_path(
+	s: State,
+	obj: ObjValue,
+	path: ArrValue,
+	inc_hidden: Option<bool>,
+) -> Result<bool> {
+	Ok(walk_path(s, Val::Obj(obj), &path, inc_hidden.unwrap_or(false))?.is_some())
+}
+
+/// Same as `std.get`, but for nested values, `default` is only evaluated if path is missing
+#[jrsonnet_macros::builtin]
+fn builtin_get_path(
+	s: State,
+	value: Any,
+	path: ArrValue,
+	default: Option<Thunk<Val>>,
+	inc_hidden: Option<bool>,
+) -> Result<Any> {
+	Ok(Any(
+		match walk_path(s.clone(), value.0, &path, inc_hidden.unwrap_or(true))? {
+			Some(v) => v,
+			None => match default {
+				Some(default) => default.evaluate(s)?,
+				None => Val::Null,
+			},
+		},
+	))
+}
+
+/// Same as `obj + { [field]+: value }`, without evaluating `value` until field is requested
+#[jrsonnet_macros::builtin]
+fn builtin_merge_field(obj: ObjValue, field: IStr, value: Thunk<Val>) -> Result<ObjValue> {
+	let mut obj = obj;
+	obj.extend_field(field)
+		.add()
+		.binding(LazyBinding::Bound(value));
+	Ok(obj)
+}
+
+/// Builds object from `[key, value]` arrays or `{key, value}` objects, fields are ordered by first occurence of key.
+/// Duplicate key overrides value of the previous one, unless `error_on_duplicate` is set
+#[jrsonnet_macros::builtin]
+fn builtin_from_pairs(
+	s: State,
+	arr: ArrValue,
+	error_on_duplicate: Option<bool>,
+) -> Result<ObjValue> {
+	let error_on_duplicate = error_on_duplicate.unwrap_or(false);
+	let mut fields: Vec<(IStr, Thunk<Val>)> = Vec::with_capacity(arr.len());
+	let mut positions: FxHashMap<IStr, usize> = FxHashMap::default();
+	for (i, pair) in arr.iter(s.clone()).enumerate() {
+		let (key, value) = s.push_description(
+			|| format!("pair <{}> evaluation", i),
+			|| {
+				Ok(match pair? {
+					Val::Arr(a) if a.len() == 2 => (
+						a.get(s.clone(), 0)?.expect("length is checked"),
+						a.get_lazy(1).expect("length is checked"),
+					),
+					Val::Obj(o) => match (
+						o.get(s.clone(), "key".into())?,
+						o.get(s.clone(), "value".into())?,
+					) {
+						(Some(key), Some(value)) => (key, Thunk::evaluated(value)),
+						_ => throw!(RuntimeError(
+							"pair object should have key and value fields".into()
+						)),
+					},
+					_ => throw!(RuntimeError(
+						"pair should be either [key, value] array or {key, value} object".into()
+					)),
+				})
+			},
+		)?;
+		let key = match key {
+			Val::Str(key) => key,
+			v => throw!(FieldMustBeStringGot(v.value_type())),
+		};
+		if let Some(&position) = positions.get(&key) {
+			if error_on_duplicate {
+				throw!(DuplicateFieldName(key));
+			}
+			fields[position].1 = value;
+		} else {
+			positions.insert(key.clone(), fields.len());
+			fields.push((key, value));
+		}
+	}
+
+	let mut out = ObjValueBuilder::with_capacity(fields.len());
+	for (key, value) in fields {
+		out.member(key)
+			.binding(s.clone(), LazyBinding::Bound(value))?;
+	}
+	Ok(out.build())
+}
+
+/// Value of object field, evaluated once requested
+#[derive(Trace)]
+struct FieldValue {
+	obj: ObjValue,
+	field: IStr,
+}
+impl ThunkValue for FieldValue {
+	type Output = Val;
+	fn get(self: Box<Self>, s: State) -> Result<Val> {
+		Ok(self.obj.get(s, self.field)?.expect("field exists"))
+	}
+}
+
+/// Inverse of `std.fromPairs`, returns `[key, value]` array for every visible field
+#[jrsonnet_macros::builtin]
+fn builtin_to_pairs(
+	obj: ObjValue,
+	#[cfg(feature = "exp-preserve-order")] preserve_order: Option<bool>,
+) -> Result<ArrValue> {
+	let fields = obj.fields(
+		#[cfg(feature = "exp-preserve-order")]
+		preserve_order.unwrap_or(false),
+	);
+	let mut out = Vec::with_capacity(fields.len());
+	for field in fields {
+		let value = Thunk::new(tb!(FieldValue {
+			obj: obj.clone(),
+			field: field.clone(),
+		}));
+		let pair: ArrValue = vec![Thunk::evaluated(Val::Str(field)), value].into();
+		out.push(Val::Arr(pair));
+	}
+	Ok(out.into())
+}
+
+/// Returns `{key, value}` object for every field, values are not evaluated until requested
+#[jrsonnet_macros::builtin]
+fn builtin_object_keys_values_ex(
+	s: State,
+	obj: ObjValue,
+	inc_hidden: bool,
+	#[cfg(feature = "exp-preserve-order")] preserve_order: Option<bool>,
+) -> Result<ArrValue> {
+	let fields = obj.fields_ex(
+		inc_hidden,
+		#[cfg(feature = "exp-preserve-order")]
+		preserve_order.unwrap_or(false),
+	);
+	let mut out = Vec::with_capacity(fields.len());
+	for field in fields {
+		let value = Thunk::new(tb!(FieldValue {
+			obj: obj.clone(),
+			field: field.clone(),
+		}));
+		let mut entry = ObjValueBuilder::with_capacity(2);
+		entry
+			.member("key".into())
+			.value(s.clone(), Val::Str(field))?;
+		entry
+			.member("value".into())
+			.binding(s.clone(), LazyBinding::Bound(value))?;
+		out.push(Val::Obj(entry.build()));
+	}
+	Ok(out.into())
+}
+
 #[jrsonnet_macros::builtin]
 fn builtin_parse_json(st: State, s: IStr) -> Result<Any> {
 	use serde_json::Value;
@@ -216,6 +556,7 @@ fn builtin_parse_json(st: State, s: IStr) -> Result<Any> {
 	Ok(Any(Value::into_untyped(value, st)?))
 }
 
+#[cfg(feature = "yaml")]
 #[jrsonnet_macros::builtin]
 fn builtin_parse_yaml(st: State, s: IStr) -> Result<Any> {
 	use serde_json::Value;
@@ -239,6 +580,64 @@ fn builtin_parse_yaml(st: State, s: IStr) -> Result<Any> {
 	}))
 }
 
+/// Parses `digits` of integer in specified base, `str` is the whole input, used in errors
+fn parse_int_digits(str: &str, digits: &str, negative: bool, base: u32) -> Result<f64> {
+	// Larger integers can't be represented exactly by f64
+	const MAX_EXACT: u64 = 1 << 53;
+
+	if digits.is_empty() {
+		throw!(RuntimeError(format!("not an integer: {:?}", str).into()));
+	}
+	let mut out: u64 = 0;
+	for c in digits.chars() {
+		let digit = c.to_digit(base).ok_or_else(|| {
+			RuntimeError(format!("invalid base {} digit {:?} in {:?}", base, c, str).into())
+		})?;
+		out = out
+			.checked_mul(base.into())
+			.and_then(|v| v.checked_add(digit.into()))
+			.filter(|v| *v <= MAX_EXACT)
+			.ok_or_else(|| RuntimeError(format!("integer {:?} is too large", str).into()))?;
+	}
+	let out = out as f64;
+	Ok(if negative { -out } else { out })
+}
+
+/// Parses integer with optional `-` sign, digits above 9 are case-insensitive latin letters
+#[jrsonnet_macros::builtin]
+fn builtin_parse_int(str: IStr, radix: Option<u32>) -> Result<f64> {
+	let radix = radix.unwrap_or(10);
+	if !(2..=36).contains(&radix) {
+		throw!(RuntimeError(
+			format!("radix {} is not in range 2..=36", radix).into()
+		));
+	}
+	let (negative, digits) = match str.strip_prefix('-') {
+		Some(digits) => (true, digits),
+		None => (false, str.as_str()),
+	};
+	parse_int_digits(&str, digits, negative, radix)
+}
+
+/// Parses integer with optional sign, and `0x`, `0o` or `0b` base prefix, i.e `-0x1F`.
+///
+/// Surrounding whitespace is not trimmed, and is reported as invalid digit
+#[jrsonnet_macros::builtin]
+fn builtin_parse_int_auto(str: IStr) -> Result<f64> {
+	let (negative, unsigned) = match str.as_bytes().first() {
+		Some(b'-') => (true, &str[1..]),
+		Some(b'+') => (false, &str[1..]),
+		_ => (false, str.as_str()),
+	};
+	let (base, digits) = match unsigned.get(..2) {
+		Some("0x" | "0X") => (16, &unsigned[2..]),
+		Some("0o" | "0O") => (8, &unsigned[2..]),
+		Some("0b" | "0B") => (2, &unsigned[2..]),
+		_ => (10, unsigned),
+	};
+	parse_int_digits(&str, digits, negative, base)
+}
+
 #[jrsonnet_macros::builtin]
 fn builtin_slice(
 	indexable: IndexableVal,
@@ -266,6 +665,9 @@ fn builtin_equals(s: State, a: Any, b: Any) -> Result<bool> {
 
 #[jrsonnet_macros::builtin]
 fn builtin_modulo(a: f64, b: f64) -> Result<f64> {
+	if b == 0.0 {
+		throw!(DivisionByZero)
+	}
 	Ok(a % b)
 }
 
@@ -292,6 +694,40 @@ fn builtin_ceil(x: f64) -> Result<f64> {
 	Ok(x.ceil())
 }
 
+#[jrsonnet_macros::builtin]
+fn builtin_round(x: f64) -> Result<f64> {
+	// Same as `std.floor(x + 0.5)`, halves are rounded up, not away from zero
+	Ok((x + 0.5).floor())
+}
+
+#[jrsonnet_macros::builtin]
+fn builtin_trunc(x: f64) -> Result<f64> {
+	Ok(x.trunc())
+}
+
+#[jrsonnet_macros::builtin]
+fn builtin_sign(n: f64) -> Result<Any> {
+	let sign = if n > 0.0 {
+		1.0
+	} else if n < 0.0 {
+		-1.0
+	} else {
+		0.0
+	};
+	Ok(Any(Val::new_checked_num(sign)?))
+}
+
+#[jrsonnet_macros::builtin]
+#[allow(non_snake_case)]
+fn builtin_clamp(x: f64, minVal: f64, maxVal: f64) -> Result<Any> {
+	if minVal > maxVal {
+		throw!(RuntimeError(
+			format!("std.clamp: minVal ({minVal}) is greater than maxVal ({maxVal})").into()
+		));
+	}
+	Ok(Any(Val::new_checked_num(x.clamp(minVal, maxVal))?))
+}
+
 #[jrsonnet_macros::builtin]
 fn builtin_log(n: f64) -> Result<f64> {
 	Ok(n.ln())
@@ -378,15 +814,28 @@ fn builtin_ext_var(s: State, x: IStr) -> Result<Any> {
 }
 
 #[jrsonnet_macros::builtin]
-fn builtin_native(s: State, name: IStr) -> Result<Any> {
-	Ok(Any(s
-		.settings()
-		.ext_natives
-		.get(&name)
-		.cloned()
-		.map_or(Val::Null, |v| {
-			Val::Func(FuncVal::Builtin(v.clone()))
-		})))
+fn builtin_native(s: State, name: IStr, arity: Option<usize>) -> Result<Any> {
+	let native = s.settings().ext_natives.get(&name).cloned();
+	let native = match native {
+		Some(native) => native,
+		None => return Ok(Any(Val::Null)),
+	};
+	if let Some(arity) = arity {
+		let params = native.params().len();
+		if params != arity {
+			throw!(RuntimeError(
+				format!("native function {name} has {params} parameters, expected {arity}").into()
+			));
+		}
+	}
+	Ok(Any(Val::Func(FuncVal::Builtin(native))))
+}
+
+#[jrsonnet_macros::builtin]
+fn builtin_native_names(s: State) -> Result<VecVal> {
+	let mut names = s.native_names();
+	names.sort_unstable();
+	Ok(VecVal(Cc::new(names.into_iter().map(Val::Str).collect())))
 }
 
 #[jrsonnet_macros::builtin]
@@ -399,6 +848,36 @@ fn builtin_filter(s: State, func: FuncVal, arr: ArrValue) -> Result<ArrValue> {
 	})
 }
 
+/// `progress(kept, scanned)` is called every `every` scanned elements and after the last one,
+/// its result is evaluated and then ignored, so it may report progress with `std.trace`,
+/// or abort filtering with `error`
+#[jrsonnet_macros::builtin]
+fn builtin_filter_with_progress(
+	s: State,
+	func: FuncVal,
+	arr: ArrValue,
+	every: usize,
+	progress: FuncVal,
+) -> Result<ArrValue> {
+	if every == 0 {
+		throw!(RuntimeError("progress interval should be positive".into()));
+	}
+	arr.filter_with_progress(
+		s.clone(),
+		|val| {
+			bool::from_untyped(
+				func.evaluate_simple(s.clone(), &(Any(val.clone()),))?,
+				s.clone(),
+			)
+		},
+		every,
+		|kept, scanned| {
+			progress.evaluate_simple(s.clone(), &(kept, scanned))?;
+			Ok(())
+		},
+	)
+}
+
 #[jrsonnet_macros::builtin]
 fn builtin_map(s: State, func: FuncVal, arr: ArrValue) -> Result<ArrValue> {
 	arr.map(s.clone(), |val| {
@@ -452,6 +931,31 @@ fn builtin_foldl(s: State, func: FuncVal, arr: ArrValue, init: Any) -> Result<An
 	Ok(Any(acc))
 }
 
+/// Same as `foldl`, but stops once `pred(acc)` returns false
+///
+/// Predicate is checked for the initial value, and then after each step,
+/// elements after the stop point are never evaluated
+#[jrsonnet_macros::builtin]
+fn builtin_reduce_while(
+	s: State,
+	func: FuncVal,
+	arr: ArrValue,
+	init: Any,
+	pred: FuncVal,
+) -> Result<Any> {
+	let mut acc = init.0;
+	for i in arr.iter_lazy() {
+		if !bool::from_untyped(
+			pred.evaluate_simple(s.clone(), &(Any(acc.clone()),))?,
+			s.clone(),
+		)? {
+			break;
+		}
+		acc = func.evaluate_simple(s.clone(), &(Any(acc), Any(i.evaluate(s.clone())?)))?;
+	}
+	Ok(Any(acc))
+}
+
 #[jrsonnet_macros::builtin]
 fn builtin_foldr(s: State, func: FuncVal, arr: ArrValue, init: Any) -> Result<Any> {
 	let mut acc = init.0;
@@ -463,7 +967,12 @@ fn builtin_foldr(s: State, func: FuncVal, arr: ArrValue, init: Any) -> Result<An
 
 #[jrsonnet_macros::builtin]
 #[allow(non_snake_case)]
-fn builtin_sort(s: State, arr: ArrValue, keyF: Option<FuncVal>) -> Result<ArrValue> {
+fn builtin_sort(
+	s: State,
+	arr: ArrValue,
+	keyF: Option<FuncVal>,
+	reverse: Option<bool>,
+) -> Result<ArrValue> {
 	if arr.len() <= 1 {
 		return Ok(arr);
 	}
@@ -471,9 +980,24 @@ fn builtin_sort(s: State, arr: ArrValue, keyF: Option<FuncVal>) -> Result<ArrVal
 		s.clone(),
 		arr.evaluated(s)?,
 		keyF.unwrap_or_else(FuncVal::identity),
+		reverse.unwrap_or(false),
 	)?))
 }
 
+#[jrsonnet_macros::builtin]
+#[allow(non_snake_case)]
+fn builtin_set(s: State, arr: ArrValue, keyF: Option<FuncVal>) -> Result<ArrValue> {
+	if arr.len() <= 1 {
+		return Ok(arr);
+	}
+	Ok(sort::set(
+		s.clone(),
+		&arr.evaluated(s)?,
+		keyF.unwrap_or_else(FuncVal::identity),
+	)?
+	.into())
+}
+
 #[jrsonnet_macros::builtin]
 fn builtin_format(s: State, str: IStr, vals: Any) -> Result<String> {
 	std_format(s, str, vals.0)
@@ -504,43 +1028,105 @@ fn builtin_decode_utf8(arr: IBytes) -> Result<IStr> {
 		.ok_or_else(|| RuntimeError("bad utf8".into()))?)
 }
 
+/// Bytes are hashed and encoded as is, strings are hashed and encoded as UTF-8
+fn bytes_input(input: &Either2<IBytes, IStr>) -> &[u8] {
+	match input {
+		Either2::A(bytes) => bytes.as_slice(),
+		Either2::B(str) => str.as_bytes(),
+	}
+}
+
 #[jrsonnet_macros::builtin]
-fn builtin_md5(str: IStr) -> Result<String> {
-	Ok(format!("{:x}", md5::compute(&str.as_bytes())))
+fn builtin_md5(input: Either![IBytes, IStr]) -> Result<String> {
+	Ok(format!("{:x}", md5::compute(bytes_input(&input))))
+}
+
+#[cfg(feature = "hashes")]
+#[jrsonnet_macros::builtin]
+fn builtin_sha1(input: Either![IBytes, IStr]) -> Result<String> {
+	use sha1::Digest;
+	Ok(format!("{:x}", sha1::Sha1::digest(bytes_input(&input))))
+}
+
+#[cfg(feature = "hashes")]
+#[jrsonnet_macros::builtin]
+fn builtin_sha256(input: Either![IBytes, IStr]) -> Result<String> {
+	use sha2::Digest;
+	Ok(format!("{:x}", sha2::Sha256::digest(bytes_input(&input))))
+}
+
+#[cfg(feature = "hashes")]
+#[jrsonnet_macros::builtin]
+fn builtin_sha512(input: Either![IBytes, IStr]) -> Result<String> {
+	use sha2::Digest;
+	Ok(format!("{:x}", sha2::Sha512::digest(bytes_input(&input))))
 }
 
 #[jrsonnet_macros::builtin]
 fn builtin_trace(s: State, loc: CallLocation, str: IStr, rest: Any) -> Result<Any> {
-	eprint!("TRACE:");
+	use std::fmt::Write;
+	let mut out = "TRACE:".to_owned();
 	if let Some(loc) = loc.0 {
 		let locs = s.map_source_locations(loc.0.clone(), &[loc.1]);
-		eprint!(" {}:{}", loc.0.short_display(), locs[0].line);
+		write!(out, " {}:{}", loc.0.short_display(), locs[0].line).unwrap();
 	}
-	eprintln!(" {}", str);
+	write!(out, " {}", str).unwrap();
+	s.write_trace_line(&out);
 	Ok(rest) as Result<Any>
 }
 
 #[jrsonnet_macros::builtin]
-fn builtin_base64(input: Either![IBytes, IStr]) -> Result<String> {
-	use Either2::*;
-	Ok(match input {
-		A(a) => base64::encode(a.as_slice()),
-		B(l) => base64::encode(l.bytes().collect::<Vec<_>>()),
+fn builtin_base64(input: Either![IBytes, IStr], url_safe: Option<bool>) -> Result<String> {
+	// Bytes from importbin are encoded as is, without converting them to array of numbers
+	Ok(base64::encode_config(
+		bytes_input(&input),
+		base64_config(url_safe),
+	))
+}
+
+fn base64_config(url_safe: Option<bool>) -> base64::Config {
+	if url_safe.unwrap_or(false) {
+		base64::URL_SAFE
+	} else {
+		base64::STANDARD
+	}
+}
+
+fn base64_decode(input: &str, url_safe: Option<bool>) -> Result<Vec<u8>> {
+	use base64::DecodeError;
+	base64::decode_config(input, base64_config(url_safe)).map_err(|e| {
+		RuntimeError(
+			match e {
+				DecodeError::InvalidByte(offset, b'=') => {
+					format!("invalid base64 padding at offset {}", offset)
+				}
+				DecodeError::InvalidByte(offset, byte)
+				| DecodeError::InvalidLastSymbol(offset, byte) => {
+					format!(
+						"invalid base64 character {:?} at offset {}",
+						char::from(byte),
+						offset
+					)
+				}
+				DecodeError::InvalidLength => format!("invalid base64 length {}", input.len()),
+			}
+			.into(),
+		)
+		.into()
 	})
 }
 
 #[jrsonnet_macros::builtin]
-fn builtin_base64_decode_bytes(input: IStr) -> Result<IBytes> {
-	Ok(base64::decode(&input.as_bytes())
-		.map_err(|_| RuntimeError("bad base64".into()))?
-		.as_slice()
-		.into())
+fn builtin_base64_decode_bytes(input: IStr, url_safe: Option<bool>) -> Result<IBytes> {
+	Ok(base64_decode(&input, url_safe)?.as_slice().into())
 }
 
 #[jrsonnet_macros::builtin]
-fn builtin_base64_decode(input: IStr) -> Result<String> {
-	let bytes = base64::decode(&input.as_bytes()).map_err(|_| RuntimeError("bad base64".into()))?;
-	Ok(String::from_utf8(bytes).map_err(|_| RuntimeError("bad utf8".into()))?)
+fn builtin_base64_decode(input: IStr, url_safe: Option<bool>) -> Result<IStr> {
+	let bytes = base64_decode(&input, url_safe)?;
+	Ok(String::from_utf8(bytes)
+		.map_err(|_| RuntimeError("bad utf8".into()))?
+		.into())
 }
 
 #[jrsonnet_macros::builtin]
@@ -603,10 +1189,23 @@ fn builtin_join(s: State, sep: IndexableVal, arr: ArrValue) -> Result<IndexableV
 }
 
 #[jrsonnet_macros::builtin]
-fn builtin_escape_string_json(str_: IStr) -> Result<String> {
-	Ok(escape_string_json(&str_))
+fn builtin_escape_string_json(str_: IStr, escape_c1: Option<bool>) -> Result<String> {
+	Ok(escape_string_json_with(&str_, escape_c1.unwrap_or(true)))
+}
+
+fn function_manifest(placeholder: Option<String>, skip: Option<bool>) -> Result<FunctionManifest> {
+	Ok(match (placeholder, skip.unwrap_or(false)) {
+		(Some(_), true) => throw!(RuntimeError(
+			"function_placeholder and skip_functions are mutually exclusive".into()
+		)),
+		(Some(placeholder), false) => FunctionManifest::Placeholder(placeholder),
+		(None, true) => FunctionManifest::Skip,
+		(None, false) => FunctionManifest::Error,
+	})
 }
 
+// Every option is a separate named argument, same as for other manifest functions
+#[allow(clippy::too_many_arguments)]
 #[jrsonnet_macros::builtin]
 fn builtin_manifest_json_ex(
 	s: State,
@@ -614,8 +1213,27 @@ fn builtin_manifest_json_ex(
 	indent: IStr,
 	newline: Option<IStr>,
 	key_val_sep: Option<IStr>,
-	#[cfg(feature = "exp-preserve-order")] preserve_order: Option<bool>,
+	preserve_order: Option<bool>,
+	max_depth: Option<usize>,
+	allow_non_finite: Option<bool>,
+	sort_arrays_by: Option<IStr>,
+	escape_c1: Option<bool>,
+	function_placeholder: Option<String>,
+	skip_functions: Option<bool>,
+	top_level_blank_lines: Option<bool>,
+	number_format: Option<IStr>,
+	ascii_only: Option<bool>,
+	trailing_comma: Option<bool>,
+	comments: Option<bool>,
 ) -> Result<String> {
+	let functions = function_manifest(function_placeholder, skip_functions)?;
+	let number_format = match number_format.as_deref() {
+		None | Some("default") => NumberFormat::Default,
+		Some("python") => NumberFormat::Python,
+		Some(v) => throw!(RuntimeError(
+			format!("unknown number format: {}, expected default or python", v).into()
+		)),
+	};
 	let newline = newline.as_deref().unwrap_or("\n");
 	let key_val_sep = key_val_sep.as_deref().unwrap_or(": ");
 	manifest_json_ex(
@@ -623,27 +1241,159 @@ fn builtin_manifest_json_ex(
 		&value.0,
 		&ManifestJsonOptions {
 			padding: &indent,
-			mtype: ManifestType::Std,
 			newline,
 			key_val_sep,
+			max_depth,
+			allow_non_finite: allow_non_finite.unwrap_or(false),
+			sort_arrays_by: sort_arrays_by.as_deref(),
+			escape_c1: escape_c1.unwrap_or(true),
+			ascii_only: ascii_only.unwrap_or(false),
+			trailing_comma: trailing_comma.unwrap_or(false),
+			comments: comments.unwrap_or(false),
+			functions: &functions,
+			top_level_blank_lines: top_level_blank_lines.unwrap_or(false),
+			number_format,
+			// Accepted regardless of features, so following arguments keep their positions
+			preserve_order,
+			..ManifestJsonOptions::default()
+		},
+	)
+}
+
+/// Same output as `std.manifestJson`, but returned as bytes array, which can be passed to
+/// binary consumers (`std.base64`, `std.md5`, ...) without being converted to array of numbers
+///
+/// This is not zero-copy: manifested buffer is copied once more when it is interned,
+/// as interned bytes are stored inline after their header, and can't adopt existing allocation
+#[jrsonnet_macros::builtin]
+fn builtin_manifest_json_bytes(
+	s: State,
+	value: Any,
+	indent: Option<IStr>,
+	#[cfg(feature = "exp-preserve-order")] preserve_order: Option<bool>,
+) -> Result<IBytes> {
+	let out = manifest_json_ex(
+		s,
+		&value.0,
+		&ManifestJsonOptions {
+			padding: indent.as_deref().unwrap_or("    "),
+			#[cfg(feature = "exp-preserve-order")]
+			preserve_order,
+			..ManifestJsonOptions::default()
+		},
+	)?;
+	Ok(out.as_bytes().into())
+}
+
+#[jrsonnet_macros::builtin]
+fn builtin_manifest_jsonl(
+	s: State,
+	value: ArrValue,
+	trailing_newline: Option<bool>,
+	#[cfg(feature = "exp-preserve-order")] preserve_order: Option<bool>,
+) -> Result<IStr> {
+	Val::Arr(value).to_jsonl(
+		s,
+		trailing_newline.unwrap_or(true),
+		#[cfg(feature = "exp-preserve-order")]
+		preserve_order.unwrap_or(false),
+	)
+}
+
+#[jrsonnet_macros::builtin]
+fn builtin_manifest_toml_ex(
+	s: State,
+	value: Any,
+	indent: IStr,
+	datetimes: Option<bool>,
+	inline_table_max_fields: Option<usize>,
+	inline_table_max_depth: Option<usize>,
+	#[cfg(feature = "exp-preserve-order")] preserve_order: Option<bool>,
+) -> Result<String> {
+	manifest::manifest_toml_ex(
+		s,
+		&value.0,
+		&manifest::ManifestTomlOptions {
+			padding: &indent,
+			inline_empty_tables: false,
+			inline_table_max_fields,
+			inline_table_max_depth: inline_table_max_depth.unwrap_or(0),
+			datetimes: datetimes.unwrap_or(false),
 			#[cfg(feature = "exp-preserve-order")]
 			preserve_order: preserve_order.unwrap_or(false),
 		},
 	)
 }
 
+#[jrsonnet_macros::builtin]
+fn builtin_manifest_ini(
+	s: State,
+	ini: ObjValue,
+	true_value: Option<IStr>,
+	false_value: Option<IStr>,
+	drop_integral_decimal: Option<bool>,
+	#[cfg(feature = "exp-preserve-order")] preserve_order: Option<bool>,
+) -> Result<String> {
+	manifest::manifest_ini(
+		s,
+		&ini,
+		&manifest::ManifestIniOptions {
+			true_value: true_value.as_deref().unwrap_or("true"),
+			false_value: false_value.as_deref().unwrap_or("false"),
+			drop_integral_decimal: drop_integral_decimal.unwrap_or(true),
+			#[cfg(feature = "exp-preserve-order")]
+			preserve_order: preserve_order.unwrap_or(false),
+		},
+	)
+}
+
+#[jrsonnet_macros::builtin]
+fn builtin_manifest_xml_jsonml(
+	s: State,
+	value: Any,
+	self_closing: Option<bool>,
+	preserve_order: Option<bool>,
+	indent: Option<Either![IStr, Null]>,
+) -> Result<String> {
+	// Accepted regardless of features, so `indent` keeps its position
+	#[cfg_attr(not(feature = "exp-preserve-order"), allow(unused_variables))]
+	let preserve_order = manifest::resolve_preserve_order(preserve_order)?;
+	let indent = match indent {
+		Some(Either2::A(indent)) => Some(indent),
+		Some(Either2::B(Null)) | None => None,
+	};
+	manifest::manifest_xml_jsonml(
+		s,
+		&value.0,
+		&manifest::ManifestXmlOptions {
+			self_closing: self_closing.unwrap_or(false),
+			indent: indent.as_deref(),
+			#[cfg(feature = "exp-preserve-order")]
+			preserve_order,
+		},
+	)
+}
+
+#[cfg(feature = "yaml")]
+#[allow(clippy::too_many_arguments)]
 #[jrsonnet_macros::builtin]
 fn builtin_manifest_yaml_doc(
 	s: State,
 	value: Any,
 	indent_array_in_object: Option<bool>,
 	quote_keys: Option<bool>,
-	#[cfg(feature = "exp-preserve-order")] preserve_order: Option<bool>,
+	preserve_order: Option<bool>,
+	function_placeholder: Option<String>,
+	skip_functions: Option<bool>,
+	comments: Option<bool>,
+	escape_c1: Option<bool>,
+	binary: Option<bool>,
 ) -> Result<String> {
-	manifest_yaml_ex(
+	let functions = function_manifest(function_placeholder, skip_functions)?;
+	manifest::manifest_yaml_ex(
 		s,
 		&value.0,
-		&ManifestYamlOptions {
+		&manifest::ManifestYamlOptions {
 			padding: "  ",
 			arr_element_padding: if indent_array_in_object.unwrap_or(false) {
 				"  "
@@ -651,8 +1401,13 @@ fn builtin_manifest_yaml_doc(
 				""
 			},
 			quote_keys: quote_keys.unwrap_or(true),
-			#[cfg(feature = "exp-preserve-order")]
-			preserve_order: preserve_order.unwrap_or(false),
+			functions: &functions,
+			comments: comments.unwrap_or(false),
+			escape_c1: escape_c1.unwrap_or(true),
+			validate_field_names: false,
+			// Accepted regardless of features, so following arguments keep their positions
+			preserve_order,
+			binary: binary.unwrap_or(false),
 		},
 	)
 }
@@ -689,6 +1444,49 @@ fn builtin_ascii_lower(str: IStr) -> Result<String> {
 	Ok(str.to_ascii_lowercase())
 }
 
+// Path helpers work with `/`-separated paths, following POSIX `basename`/`dirname`,
+// independently of host OS
+
+#[jrsonnet_macros::builtin]
+fn builtin_basename(path: IStr) -> Result<String> {
+	let trimmed = path.trim_end_matches('/');
+	if trimmed.is_empty() {
+		return Ok(if path.is_empty() { "" } else { "/" }.to_owned());
+	}
+	Ok(trimmed[trimmed.rfind('/').map_or(0, |idx| idx + 1)..].to_owned())
+}
+
+#[jrsonnet_macros::builtin]
+fn builtin_dirname(path: IStr) -> Result<String> {
+	let trimmed = path.trim_end_matches('/');
+	if trimmed.is_empty() {
+		return Ok(if path.is_empty() { "." } else { "/" }.to_owned());
+	}
+	Ok(match trimmed.rfind('/') {
+		None => ".",
+		Some(idx) => match trimmed[..idx].trim_end_matches('/') {
+			"" => "/",
+			dir => dir,
+		},
+	}
+	.to_owned())
+}
+
+/// Empty parts are skipped, and absolute part discards everything before it
+#[jrsonnet_macros::builtin]
+fn builtin_join_path(parts: Vec<IStr>) -> Result<String> {
+	let mut out = String::new();
+	for part in parts.iter().filter(|p| !p.is_empty()) {
+		if part.starts_with('/') {
+			out.clear();
+		} else if !out.is_empty() && !out.ends_with('/') {
+			out.push('/');
+		}
+		out.push_str(part);
+	}
+	Ok(out)
+}
+
 #[jrsonnet_macros::builtin]
 fn builtin_member(s: State, arr: IndexableVal, x: Any) -> Result<bool> {
 	match arr {
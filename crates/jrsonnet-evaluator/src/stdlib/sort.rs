@@ -1,10 +1,14 @@
+use std::hash::Hasher;
+
 use jrsonnet_gcmodule::{Cc, Trace};
+use rustc_hash::{FxHashMap, FxHasher};
 
 use crate::{
 	error::{Error, LocError, Result},
 	function::FuncVal,
 	throw,
 	typed::Any,
+	val::{equals, hash_val},
 	State, Val,
 };
 
@@ -50,6 +54,10 @@ fn get_sort_type<T>(
 	let mut sort_type = SortKeyType::Unknown;
 	for i in values.iter_mut() {
 		let i = key_getter(i);
+		// Jsonnet code can't produce NaN, but native functions can
+		if matches!(i, Val::Num(n) if n.is_nan()) {
+			throw!(Error::RuntimeError("cannot sort NaN".into()));
+		}
 		match (i, sort_type) {
 			(Val::Str(_), SortKeyType::Unknown) => sort_type = SortKeyType::String,
 			(Val::Num(_), SortKeyType::Unknown) => sort_type = SortKeyType::Number,
@@ -64,11 +72,17 @@ fn get_sort_type<T>(
 }
 
 /// * `key_getter` - None, if identity sort required
-pub fn sort(s: State, values: Cc<Vec<Val>>, key_getter: FuncVal) -> Result<Cc<Vec<Val>>> {
+/// * `reverse` - sort in descending order, values with equal keys still keep their original order
+pub fn sort(
+	s: State,
+	values: Cc<Vec<Val>>,
+	key_getter: FuncVal,
+	reverse: bool,
+) -> Result<Cc<Vec<Val>>> {
 	if values.len() <= 1 {
 		return Ok(values);
 	}
-	if key_getter.is_identity() {
+	if key_getter.is_identity() && !reverse {
 		// Fast path, identity key getter
 		let mut values = (*values).clone();
 		let sort_type = get_sort_type(&mut values, |k| k)?;
@@ -85,26 +99,103 @@ pub fn sort(s: State, values: Cc<Vec<Val>>, key_getter: FuncVal) -> Result<Cc<Ve
 		};
 		Ok(Cc::new(values))
 	} else {
-		// Slow path, user provided key getter
-		let mut vk = Vec::with_capacity(values.len());
-		for value in values.iter() {
-			vk.push((
-				value.clone(),
-				key_getter.evaluate_simple(s.clone(), &(Any(value.clone()),))?,
-			));
-		}
-		let sort_type = get_sort_type(&mut vk, |v| &mut v.1)?;
-		match sort_type {
-			SortKeyType::Number => vk.sort_by_key(|v| match v.1 {
-				Val::Num(n) => NonNaNf64(n),
-				_ => unreachable!(),
-			}),
-			SortKeyType::String => vk.sort_by_key(|v| match &v.1 {
-				Val::Str(s) => s.clone(),
-				_ => unreachable!(),
-			}),
-			SortKeyType::Unknown => unreachable!(),
+		// Slow path, user provided key getter, keys are evaluated once per element
+		Ok(Cc::new(
+			sort_keyed_pairs(with_keys(s, &values, &key_getter)?, reverse)?
+				.into_iter()
+				.map(|v| v.0)
+				.collect(),
+		))
+	}
+}
+
+fn with_keys(s: State, values: &[Val], key_getter: &FuncVal) -> Result<Vec<(Val, Val)>> {
+	let mut vk = Vec::with_capacity(values.len());
+	for value in values {
+		let key = if key_getter.is_identity() {
+			value.clone()
+		} else {
+			key_getter.evaluate_simple(s.clone(), &(Any(value.clone()),))?
 		};
-		Ok(Cc::new(vk.into_iter().map(|v| v.0).collect()))
+		vk.push((value.clone(), key));
+	}
+	Ok(vk)
+}
+
+/// Stable sort of (value, key) pairs by key
+pub fn sort_keyed(vk: Vec<(Val, Val)>) -> Result<Vec<Val>> {
+	Ok(sort_keyed_pairs(vk, false)?
+		.into_iter()
+		.map(|v| v.0)
+		.collect())
+}
+
+fn sort_keyed_pairs(mut vk: Vec<(Val, Val)>, reverse: bool) -> Result<Vec<(Val, Val)>> {
+	if vk.len() <= 1 {
+		return Ok(vk);
+	}
+	let sort_type = get_sort_type(&mut vk, |v| &mut v.1)?;
+	// Comparing swapped arguments instead of reversing sorted array, as stable sort
+	// should keep original order of equal elements in both directions
+	match sort_type {
+		SortKeyType::Number => vk.sort_by(|a, b| match (&a.1, &b.1) {
+			(Val::Num(a), Val::Num(b)) => {
+				let (a, b) = (NonNaNf64(*a), NonNaNf64(*b));
+				if reverse {
+					b.cmp(&a)
+				} else {
+					a.cmp(&b)
+				}
+			}
+			_ => unreachable!(),
+		}),
+		SortKeyType::String => vk.sort_by(|a, b| match (&a.1, &b.1) {
+			(Val::Str(a), Val::Str(b)) => {
+				if reverse {
+					b.cmp(a)
+				} else {
+					a.cmp(b)
+				}
+			}
+			_ => unreachable!(),
+		}),
+		SortKeyType::Unknown => unreachable!(),
+	};
+	Ok(vk)
+}
+
+/// Native implementation of `std.set`: sorts values by key,
+/// keeping only the first value of each group with equal keys.
+///
+/// String keys are deduplicated by hash before sorting, so duplicates aren't sorted and compared
+/// over and over again. Number keys are cheap to compare, and [`hash_val`] is weak for them,
+/// so they are deduplicated after sorting
+pub fn set(s: State, values: &[Val], key_getter: FuncVal) -> Result<Vec<Val>> {
+	let vk = with_keys(s.clone(), values, &key_getter)?;
+	if !vk.iter().all(|(_, k)| matches!(k, Val::Str(_))) {
+		let mut out: Vec<(Val, Val)> = Vec::with_capacity(vk.len());
+		for (value, key) in sort_keyed_pairs(vk, false)? {
+			match out.last() {
+				Some((_, last)) if equals(s.clone(), last, &key)? => {}
+				_ => out.push((value, key)),
+			}
+		}
+		return Ok(out.into_iter().map(|v| v.0).collect());
+	}
+
+	let mut buckets: FxHashMap<u64, Vec<usize>> = FxHashMap::default();
+	let mut unique: Vec<(Val, Val)> = Vec::new();
+	'values: for (value, key) in vk {
+		let mut hasher = FxHasher::default();
+		hash_val(s.clone(), &key, &mut hasher)?;
+		let bucket = buckets.entry(hasher.finish()).or_default();
+		for &idx in bucket.iter() {
+			if equals(s.clone(), &unique[idx].1, &key)? {
+				continue 'values;
+			}
+		}
+		bucket.push(unique.len());
+		unique.push((value, key));
 	}
+	sort_keyed(unique)
 }
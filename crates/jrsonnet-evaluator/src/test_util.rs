@@ -0,0 +1,38 @@
+//! Helpers for embedders, which want to test their values against evaluator
+use serde_json::Value;
+
+use crate::{
+	error::{Error::*, Result},
+	throw_runtime,
+	typed::Typed,
+	val::equals,
+	State, Val,
+};
+
+/// Asserts that `std.parseJson(std.manifestJson(v)) == v`
+///
+/// Value should only consist of json-compatible values, i.e it can't contain functions
+pub fn assert_json_roundtrip(s: State, v: &Val) -> Result<()> {
+	let manifested = v.to_std_json(
+		s.clone(),
+		"    ",
+		#[cfg(feature = "exp-preserve-order")]
+		false,
+	)?;
+	let value: Value = serde_json::from_str(&manifested)
+		.map_err(|e| RuntimeError(format!("failed to parse json: {}", e).into()))?;
+	let parsed = Value::into_untyped(value, s.clone())?;
+	if !equals(s.clone(), v, &parsed)? {
+		throw_runtime!(
+			"json roundtrip mismatch:\nmanifested: {}\nparsed: {}",
+			manifested,
+			parsed.to_std_json(
+				s,
+				"    ",
+				#[cfg(feature = "exp-preserve-order")]
+				false,
+			)?,
+		);
+	}
+	Ok(())
+}
@@ -5,7 +5,10 @@ use std::path::{Path, PathBuf};
 use jrsonnet_parser::Source;
 pub use location::*;
 
-use crate::{error::Error, LocError, State};
+use crate::{
+	error::{Error, StackTraceElement},
+	LocError, State,
+};
 
 /// The way paths should be displayed
 pub enum PathResolver {
@@ -39,6 +42,61 @@ impl PathResolver {
 	}
 }
 
+/// Decides which stack trace frames are displayed,
+/// consecutive hidden frames are collapsed into a single marker line
+pub enum FrameFilter {
+	/// Display every frame
+	None,
+	/// Hide frames located in the standard library
+	Std,
+	/// Hide frames for which predicate returns true
+	Custom(Box<dyn Fn(&StackTraceElement) -> bool>),
+}
+
+impl FrameFilter {
+	pub fn hides(&self, el: &StackTraceElement) -> bool {
+		match self {
+			Self::None => false,
+			Self::Std => matches!(&el.location, Some(l) if l.0.repr() == Err("<std>")),
+			Self::Custom(f) => f(el),
+		}
+	}
+
+	fn marker(&self, hidden: usize) -> String {
+		let kind = match self {
+			Self::Std => "std ",
+			_ => "hidden ",
+		};
+		format!(
+			"... {} {}frame{} ...",
+			hidden,
+			kind,
+			if hidden == 1 { "" } else { "s" }
+		)
+	}
+}
+
+enum TraceFrame<'e> {
+	Shown(&'e StackTraceElement),
+	Hidden(usize),
+}
+
+fn filter_frames<'e>(filter: &FrameFilter, trace: &'e [StackTraceElement]) -> Vec<TraceFrame<'e>> {
+	let mut out = Vec::with_capacity(trace.len());
+	for el in trace {
+		if !filter.hides(el) {
+			out.push(TraceFrame::Shown(el));
+			continue;
+		}
+		if let Some(TraceFrame::Hidden(hidden)) = out.last_mut() {
+			*hidden += 1;
+		} else {
+			out.push(TraceFrame::Hidden(1));
+		}
+	}
+	out
+}
+
 /// Implements pretty-printing of traces
 #[allow(clippy::module_name_repetitions)]
 pub trait TraceFormat {
@@ -77,7 +135,11 @@ fn print_code_location(
 /// vanilla-like jsonnet formatting
 pub struct CompactFormat {
 	pub resolver: PathResolver,
+	/// Indentation of every trace frame
 	pub padding: usize,
+	/// Written between aligned `file:line:col:` and frame description
+	pub separator: String,
+	pub filter: FrameFilter,
 }
 
 impl TraceFormat for CompactFormat {
@@ -120,11 +182,13 @@ impl TraceFormat for CompactFormat {
 			print_code_location(&mut n, &location, &location).unwrap();
 			write!(out, "{:<p$}{}", "", n, p = self.padding,)?;
 		}
-		let file_names = error
-			.trace()
-			.0
+		let frames = filter_frames(&self.filter, &error.trace().0);
+		let file_names = frames
 			.iter()
-			.map(|el| &el.location)
+			.map(|frame| match frame {
+				TraceFrame::Shown(el) => el.location.as_ref(),
+				TraceFrame::Hidden(_) => None,
+			})
 			.map(|location| {
 				use std::fmt::Write;
 				#[allow(clippy::option_if_let_else)]
@@ -151,14 +215,28 @@ impl TraceFormat for CompactFormat {
 			.map(String::len)
 			.max()
 			.unwrap_or(0);
-		for (el, file) in error.trace().0.iter().zip(file_names) {
+		for (frame, file) in frames.iter().zip(file_names) {
 			writeln!(out)?;
+			let el = match frame {
+				TraceFrame::Shown(el) => el,
+				TraceFrame::Hidden(hidden) => {
+					write!(
+						out,
+						"{:<p$}{}",
+						"",
+						self.filter.marker(*hidden),
+						p = self.padding
+					)?;
+					continue;
+				}
+			};
 			if let Some(file) = file {
 				write!(
 					out,
-					"{:<p$}{:<w$} {}",
+					"{:<p$}{:<w$}{}{}",
 					"",
 					file,
+					self.separator,
 					el.desc,
 					p = self.padding,
 					w = align
@@ -207,6 +285,7 @@ impl TraceFormat for JsFormat {
 #[cfg(feature = "explaining-traces")]
 pub struct ExplainingFormat {
 	pub resolver: PathResolver,
+	pub filter: FrameFilter,
 }
 #[cfg(feature = "explaining-traces")]
 impl TraceFormat for ExplainingFormat {
@@ -241,9 +320,15 @@ impl TraceFormat for ExplainingFormat {
 				"syntax error",
 			)?;
 		}
-		let trace = &error.trace();
-		for item in &trace.0 {
+		for frame in filter_frames(&self.filter, &error.trace().0) {
 			writeln!(out)?;
+			let item = match frame {
+				TraceFrame::Shown(item) => item,
+				TraceFrame::Hidden(hidden) => {
+					write!(out, "{}", self.filter.marker(hidden))?;
+					continue;
+				}
+			};
 			let desc = &item.desc;
 			if let Some(source) = &item.location {
 				let start_end = s.map_source_locations(source.0.clone(), &[source.1, source.2]);
@@ -10,7 +10,7 @@ use crate::{
 	function::{FuncDesc, FuncVal},
 	throw,
 	typed::CheckType,
-	val::{ArrValue, IndexableVal},
+	val::{whole_number, ArrValue, IndexableVal},
 	ObjValue, ObjValueBuilder, State, Val,
 };
 
@@ -39,16 +39,16 @@ macro_rules! impl_int {
 				<Self as Typed>::TYPE.check(s, &value)?;
 				match value {
 					Val::Num(n) => {
-						#[allow(clippy::float_cmp)]
-						if n.trunc() != n {
-							throw!(RuntimeError(
+						let n = match whole_number(n) {
+							Some(n) => n,
+							None => throw!(RuntimeError(
 								format!(
 									"cannot convert number with fractional part to {}",
 									stringify!($ty)
 								)
 								.into()
-							))
-						}
+							)),
+						};
 						Ok(n as Self)
 					}
 					_ => unreachable!(),
@@ -97,16 +97,16 @@ macro_rules! impl_bounded_int {
 				<Self as Typed>::TYPE.check(s, &value)?;
 				match value {
 					Val::Num(n) => {
-						#[allow(clippy::float_cmp)]
-						if n.trunc() != n {
-							throw!(RuntimeError(
+						let n = match whole_number(n) {
+							Some(n) => n,
+							None => throw!(RuntimeError(
 								format!(
 									"cannot convert number with fractional part to {}",
 									stringify!($ty)
 								)
 								.into()
-							))
-						}
+							)),
+						};
 						Ok(Self(n as $ty))
 					}
 					_ => unreachable!(),
@@ -176,12 +176,12 @@ impl Typed for usize {
 		<Self as Typed>::TYPE.check(s, &value)?;
 		match value {
 			Val::Num(n) => {
-				#[allow(clippy::float_cmp)]
-				if n.trunc() != n {
-					throw!(RuntimeError(
+				let n = match whole_number(n) {
+					Some(n) => n,
+					None => throw!(RuntimeError(
 						"cannot convert number with fractional part to usize".into()
-					))
-				}
+					)),
+				};
 				Ok(n as Self)
 			}
 			_ => unreachable!(),
@@ -84,14 +84,17 @@ impl Display for TypeLocErrorList {
 	}
 }
 
-fn push_type_description(
+/// Runs `item`, appending `path` to location of type errors produced by it.
+///
+/// Used by [`Typed`] derive to report location of invalid nested values
+pub fn push_type_description<T>(
 	s: State,
-	error_reason: impl Fn() -> String,
-	path: impl Fn() -> ValuePathItem,
-	item: impl Fn() -> Result<()>,
-) -> Result<()> {
+	error_reason: impl FnOnce() -> String,
+	path: impl FnOnce() -> ValuePathItem,
+	item: impl FnOnce() -> Result<T>,
+) -> Result<T> {
 	s.push_description(error_reason, || match item() {
-		Ok(_) => Ok(()),
+		Ok(v) => Ok(v),
 		Err(mut e) => {
 			if let Error::TypeError(e) = &mut e.error_mut() {
 				(e.1).0.push(path());
@@ -101,9 +104,13 @@ fn push_type_description(
 	})
 }
 
-// TODO: check_fast for fast path of union type checking
 pub trait CheckType {
 	fn check(&self, s: State, value: &Val) -> Result<()>;
+	/// Checks value without evaluating its elements or building errors,
+	/// returns `None` if that is not possible, and [`Self::check`] should be used
+	fn check_fast(&self, _value: &Val) -> Option<bool> {
+		None
+	}
 }
 
 impl CheckType for ValType {
@@ -115,10 +122,14 @@ impl CheckType for ValType {
 		}
 		Ok(())
 	}
+	fn check_fast(&self, value: &Val) -> Option<bool> {
+		Some(value.value_type() == *self)
+	}
 }
 
+/// Single step of path to invalid value, displayed as `self.field[0]`
 #[derive(Clone, Debug, Trace)]
-enum ValuePathItem {
+pub enum ValuePathItem {
 	Field(#[trace(skip)] Rc<str>),
 	Index(u64),
 }
@@ -145,8 +156,74 @@ impl Display for ValuePathStack {
 }
 
 impl CheckType for ComplexValType {
+	fn check_fast(&self, value: &Val) -> Option<bool> {
+		fn any_of<'t>(
+			types: impl Iterator<Item = &'t ComplexValType>,
+			value: &Val,
+		) -> Option<bool> {
+			let mut decided = true;
+			for ty in types {
+				match ty.check_fast(value) {
+					Some(true) => return Some(true),
+					Some(false) => {}
+					None => decided = false,
+				}
+			}
+			if decided {
+				Some(false)
+			} else {
+				None
+			}
+		}
+		fn all_of<'t>(
+			types: impl Iterator<Item = &'t ComplexValType>,
+			value: &Val,
+		) -> Option<bool> {
+			let mut decided = true;
+			for ty in types {
+				match ty.check_fast(value) {
+					Some(false) => return Some(false),
+					Some(true) => {}
+					None => decided = false,
+				}
+			}
+			if decided {
+				Some(true)
+			} else {
+				None
+			}
+		}
+		match self {
+			Self::Any => Some(true),
+			Self::Simple(t) => t.check_fast(value),
+			Self::Char => Some(matches!(value, Val::Str(s) if s.chars().count() == 1)),
+			Self::BoundedNumber(from, to) => Some(match value {
+				Val::Num(n) => {
+					!(from.map_or(false, |from| from > *n) || to.map_or(false, |to| to < *n))
+				}
+				_ => false,
+			}),
+			// Elements and fields need to be checked too
+			Self::Array(_) | Self::ArrayRef(_) => match value {
+				Val::Arr(_) => None,
+				_ => Some(false),
+			},
+			Self::ObjectRef(_) => match value {
+				Val::Obj(_) => None,
+				_ => Some(false),
+			},
+			Self::Union(types) => any_of(types.iter(), value),
+			Self::UnionRef(types) => any_of(types.iter().copied(), value),
+			Self::Sum(types) => all_of(types.iter(), value),
+			Self::SumRef(types) => all_of(types.iter().copied(), value),
+		}
+	}
+
 	#[allow(clippy::too_many_lines)]
 	fn check(&self, s: State, value: &Val) -> Result<()> {
+		if self.check_fast(value) == Some(true) {
+			return Ok(());
+		}
 		match self {
 			Self::Any => Ok(()),
 			Self::Simple(t) => t.check(s, value),
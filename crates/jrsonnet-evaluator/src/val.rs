@@ -1,17 +1,29 @@
-use std::{cell::RefCell, fmt::Debug, rc::Rc};
+use std::{
+	cell::{Ref, RefCell},
+	fmt::Debug,
+	hash::{Hash, Hasher},
+	io, iter,
+	rc::Rc,
+};
 
 use jrsonnet_gcmodule::{Cc, Trace};
 use jrsonnet_interner::{IBytes, IStr};
 use jrsonnet_types::ValType;
 
+#[cfg(feature = "yaml")]
+use crate::stdlib::manifest::{
+	manifest_yaml_ex, manifest_yaml_ex_to, FunctionManifest, ManifestYamlOptions,
+};
 use crate::{
 	error::{Error::*, LocError},
 	function::FuncVal,
 	gc::{GcHashMap, TraceBox},
 	stdlib::manifest::{
-		manifest_json_ex, manifest_yaml_ex, ManifestJsonOptions, ManifestType, ManifestYamlOptions,
+		manifest_csv, manifest_json_ex, manifest_json_ex_to, manifest_toml_ex, manifest_xml_jsonml,
+		AnsiStyler, JsonStyler, ManifestCsvOptions, ManifestJsonOptions, ManifestTomlOptions,
+		ManifestType, ManifestXmlOptions, NoStyle,
 	},
-	throw, ObjValue, Result, State, Unbound, WeakObjValue,
+	tb, throw, ObjValue, ObjValueBuilder, Result, State, Unbound, WeakObjValue,
 };
 
 pub trait ThunkValue: Trace {
@@ -59,6 +71,7 @@ where
 		} else {
 			unreachable!()
 		};
+		s.count_thunk_forced();
 		let new_value = match value.0.get(s) {
 			Ok(v) => v,
 			Err(e) => {
@@ -126,17 +139,59 @@ impl<T: Trace> PartialEq for Thunk<T> {
 
 #[derive(Clone)]
 pub enum ManifestFormat {
+	#[cfg(feature = "yaml")]
 	YamlStream(Box<ManifestFormat>),
+	#[cfg(feature = "yaml")]
 	Yaml {
 		padding: usize,
 		#[cfg(feature = "exp-preserve-order")]
 		preserve_order: bool,
 	},
 	Json {
+		/// Indentation of nested values, i.e `"\t"` for hard tabs,
+		/// empty string makes output minified
+		padding: String,
+		#[cfg(feature = "exp-preserve-order")]
+		preserve_order: bool,
+	},
+	/// Json with comments, string fields named `__comment_<key>__` are written as `//` lines
+	/// above `<key>`, see [`ManifestJsonOptions::comments`]
+	Jsonc {
+		/// Indentation of nested values, comments are omitted from minified output
+		padding: String,
+		#[cfg(feature = "exp-preserve-order")]
+		preserve_order: bool,
+	},
+	/// Newline-delimited json, every element of top-level array is written minified on its own line
+	///
+	/// Empty array is written as empty string, even with `trailing_newline`
+	Jsonl {
+		trailing_newline: bool,
+		#[cfg(feature = "exp-preserve-order")]
+		preserve_order: bool,
+	},
+	Toml {
+		/// Indentation of nested tables and multiline array elements
 		padding: usize,
 		#[cfg(feature = "exp-preserve-order")]
 		preserve_order: bool,
 	},
+	/// Expects `JsonML` array as output
+	Xml {
+		/// Indentation of nested elements, empty string writes output on a single line
+		indent: String,
+		/// Write elements without children as `<tag/>`
+		self_closing: bool,
+		#[cfg(feature = "exp-preserve-order")]
+		preserve_order: bool,
+	},
+	/// Expects array of objects as output, writes one CSV row per object
+	Csv {
+		/// Columns to write, derived from fields of the first object if not set
+		headers: Option<Vec<IStr>>,
+		#[cfg(feature = "exp-preserve-order")]
+		preserve_order: bool,
+	},
 	ToString,
 	String,
 }
@@ -144,9 +199,16 @@ impl ManifestFormat {
 	#[cfg(feature = "exp-preserve-order")]
 	fn preserve_order(&self) -> bool {
 		match self {
+			#[cfg(feature = "yaml")]
 			ManifestFormat::YamlStream(s) => s.preserve_order(),
+			#[cfg(feature = "yaml")]
 			ManifestFormat::Yaml { preserve_order, .. } => *preserve_order,
 			ManifestFormat::Json { preserve_order, .. } => *preserve_order,
+			ManifestFormat::Jsonc { preserve_order, .. } => *preserve_order,
+			ManifestFormat::Jsonl { preserve_order, .. } => *preserve_order,
+			ManifestFormat::Toml { preserve_order, .. } => *preserve_order,
+			ManifestFormat::Xml { preserve_order, .. } => *preserve_order,
+			ManifestFormat::Csv { preserve_order, .. } => *preserve_order,
 			ManifestFormat::ToString => false,
 			ManifestFormat::String => false,
 		}
@@ -184,6 +246,196 @@ impl Slice {
 	}
 }
 
+/// Arrays with at most this many elements are copied on concatenation, instead of being referenced
+const CONCAT_COPY_THRESHOLD: usize = 64;
+
+/// Append-only list, shared between values which only differ by appended items
+///
+/// Every value owns first `len` items of the shared buffer. Items are appended in place if value
+/// owns the whole buffer, otherwise owned items are copied to a new buffer first.
+/// This way, chained concatenations don't copy already appended items again,
+/// even if intermediate values are still referenced
+#[derive(Debug, Clone, Trace)]
+struct AppendList<T: Trace> {
+	items: Cc<RefCell<Vec<T>>>,
+	len: usize,
+}
+impl<T: Trace + Clone> AppendList<T> {
+	fn new() -> Self {
+		Self {
+			items: Cc::new(RefCell::new(Vec::new())),
+			len: 0,
+		}
+	}
+	const fn len(&self) -> usize {
+		self.len
+	}
+	fn items(&self) -> Ref<'_, [T]> {
+		let len = self.len;
+		Ref::map(self.items.borrow(), |items| &items[..len])
+	}
+	fn get(&self, index: usize) -> Option<T> {
+		self.items().get(index).cloned()
+	}
+	fn extend(&mut self, new: impl IntoIterator<Item = T>) {
+		// Buffer may be borrowed while its items are evaluated, which may append to it
+		if let Ok(mut items) = self.items.try_borrow_mut() {
+			if items.len() == self.len {
+				items.extend(new);
+				self.len = items.len();
+				return;
+			}
+		}
+		let mut items = self.items().to_vec();
+		items.extend(new);
+		self.len = items.len();
+		self.items = Cc::new(RefCell::new(items));
+	}
+}
+
+/// Flat list of concatenated arrays, allowing to find element in `O(log n)` of part count
+///
+/// All lists are [`AppendList`]s, so concatenation result shares them with the left operand,
+/// and `a + b` doesn't copy parts of `a`, even if `a` is still referenced elsewhere
+#[derive(Debug, Clone, Trace)]
+pub struct Concat {
+	/// Non-empty arrays, none of which is [`ArrValue::Extended`] itself
+	parts: AppendList<ArrValue>,
+	/// Index of the first element after every part in the resulting array
+	ends: AppendList<usize>,
+	/// Elements of small arrays, appended after the last part
+	tail: AppendList<Thunk<Val>>,
+}
+impl Concat {
+	fn new() -> Self {
+		Self {
+			parts: AppendList::new(),
+			ends: AppendList::new(),
+			tail: AppendList::new(),
+		}
+	}
+	fn parts_len(&self) -> usize {
+		self.ends.items().last().copied().unwrap_or(0)
+	}
+	fn len(&self) -> usize {
+		self.parts_len() + self.tail.len()
+	}
+	fn push(&mut self, part: ArrValue) {
+		debug_assert!(!part.is_empty() && !matches!(part, ArrValue::Extended(_)));
+		if part.len() <= CONCAT_COPY_THRESHOLD {
+			self.tail.extend(part.iter_lazy());
+			return;
+		}
+		self.flush_tail();
+		self.push_part(part);
+	}
+	fn push_part(&mut self, part: ArrValue) {
+		let end = self.parts_len() + part.len();
+		self.ends.extend(iter::once(end));
+		self.parts.extend(iter::once(part));
+	}
+	/// Moves tail elements to their own part, so the next part may be appended after them
+	fn flush_tail(&mut self) {
+		if self.tail.len() != 0 {
+			let tail = ArrValue::Lazy(Cc::new(self.tail.items().to_vec()));
+			self.tail = AppendList::new();
+			self.push_part(tail);
+		}
+	}
+	fn append(&mut self, other: &Self) {
+		for part in other.parts.items().iter() {
+			self.push(part.clone());
+		}
+		if other.tail.len() != 0 {
+			self.tail.extend(other.tail.items().to_vec());
+		}
+	}
+	/// Returns part holding element with specified index, and index of element in this part,
+	/// `index` should be less than [`Self::parts_len`]
+	fn locate_part(&self, index: usize) -> (ArrValue, usize) {
+		let ends = self.ends.items();
+		let part = ends.partition_point(|&end| end <= index);
+		let start = if part == 0 { 0 } else { ends[part - 1] };
+		(
+			self.parts
+				.get(part)
+				.expect("ends and parts have the same length"),
+			index - start,
+		)
+	}
+	fn get(&self, s: State, index: usize) -> Result<Option<Val>> {
+		let parts_len = self.parts_len();
+		if index < parts_len {
+			let (part, index) = self.locate_part(index);
+			return part.get(s, index);
+		}
+		self.tail
+			.get(index - parts_len)
+			.map(|v| v.evaluate(s))
+			.transpose()
+	}
+	fn get_lazy(&self, index: usize) -> Option<Thunk<Val>> {
+		let parts_len = self.parts_len();
+		if index < parts_len {
+			let (part, index) = self.locate_part(index);
+			return part.get_lazy(index);
+		}
+		self.tail.get(index - parts_len)
+	}
+}
+
+/// Array of `len` elements, every element is computed by calling `func` with its index
+/// on the first access, so i.e `std.makeArray(1e6, f)[0]` calls `f` only once
+#[derive(Trace)]
+pub struct ComputedArray {
+	func: FuncVal,
+	len: usize,
+	/// Already requested elements, only these are stored, so sparse access to huge array is cheap
+	elements: RefCell<GcHashMap<usize, Thunk<Val>>>,
+}
+impl Debug for ComputedArray {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("ComputedArray")
+			.field("len", &self.len)
+			.finish_non_exhaustive()
+	}
+}
+impl ComputedArray {
+	fn get_lazy(&self, index: usize) -> Option<Thunk<Val>> {
+		#[derive(Trace)]
+		struct ArrayElement {
+			func: FuncVal,
+			index: usize,
+		}
+		impl ThunkValue for ArrayElement {
+			type Output = Val;
+			fn get(self: Box<Self>, s: State) -> Result<Val> {
+				let index = self.index;
+				s.push_description(
+					|| format!("std.makeArray element <{}>", index),
+					|| self.func.evaluate_simple(s.clone(), &(index as f64,)),
+				)
+			}
+		}
+
+		if index >= self.len {
+			return None;
+		}
+		Some(
+			self.elements
+				.borrow_mut()
+				.entry(index)
+				.or_insert_with(|| {
+					Thunk::new(tb!(ArrayElement {
+						func: self.func.clone(),
+						index,
+					}))
+				})
+				.clone(),
+		)
+	}
+}
+
 #[derive(Debug, Clone, Trace)]
 // may contrain other ArrValue
 #[trace(tracking(force))]
@@ -191,8 +443,11 @@ pub enum ArrValue {
 	Bytes(#[trace(skip)] IBytes),
 	Lazy(Cc<Vec<Thunk<Val>>>),
 	Eager(Cc<Vec<Val>>),
-	Extended(Box<(Self, Self)>),
+	/// Result of [`ArrValue::extended`], nested concatenations are always flattened
+	Extended(Cc<Concat>),
+	/// Inclusive range of integers, elements are computed on access
 	Range(i32, i32),
+	Computed(Cc<ComputedArray>),
 	Slice(Box<Slice>),
 	Reversed(Box<Self>),
 }
@@ -205,6 +460,42 @@ impl ArrValue {
 		Self::Eager(Cc::new(Vec::new()))
 	}
 
+	/// Concatenates arrays, keeping result flat: small arrays are copied, big ones are referenced
+	/// lazily as parts of [`Concat`], so chained concatenations don't build deep trees
+	pub fn extended(a: Self, b: Self) -> Self {
+		if a.is_empty() {
+			return b;
+		}
+		if b.is_empty() {
+			return a;
+		}
+		if a.len() + b.len() <= CONCAT_COPY_THRESHOLD {
+			return Self::concat_copy(&a, &b);
+		}
+		let mut concat = match a {
+			// Shares lists of `a`, appending to them in place, unless something else was already
+			// appended to `a`, in which case only this concatenation copies them
+			Self::Extended(concat) => Concat::clone(&concat),
+			a => {
+				let mut concat = Concat::new();
+				concat.push(a);
+				concat
+			}
+		};
+		match &b {
+			Self::Extended(other) => concat.append(other),
+			b => concat.push(b.clone()),
+		}
+		Self::Extended(Cc::new(concat))
+	}
+
+	fn concat_copy(a: &Self, b: &Self) -> Self {
+		let mut out = Vec::with_capacity(a.len() + b.len());
+		out.extend(a.iter_lazy());
+		out.extend(b.iter_lazy());
+		Self::Lazy(Cc::new(out))
+	}
+
 	/// # Panics
 	/// If a > b
 	pub fn new_range(a: i32, b: i32) -> Self {
@@ -212,6 +503,16 @@ impl ArrValue {
 		Self::Range(a, b)
 	}
 
+	/// Array of `len` elements, produced by calling `func` with element index,
+	/// see [`ComputedArray`]
+	pub fn new_computed(len: usize, func: FuncVal) -> Self {
+		Self::Computed(Cc::new(ComputedArray {
+			func,
+			len,
+			elements: RefCell::new(GcHashMap::new()),
+		}))
+	}
+
 	/// # Panics
 	/// If passed numbers are incorrect
 	#[must_use]
@@ -223,6 +524,11 @@ impl ArrValue {
 		assert!(from < to);
 		assert!(step > 0);
 
+		// Keep bytes as bytes, so builtins accepting IBytes can use them without conversion
+		if let (Self::Bytes(bytes), 1) = (&self, step) {
+			return Self::Bytes(bytes[from..to].into());
+		}
+
 		Self::Slice(Box::new(Slice {
 			inner: self,
 			from: from as u32,
@@ -236,8 +542,9 @@ impl ArrValue {
 			Self::Bytes(i) => i.len(),
 			Self::Lazy(l) => l.len(),
 			Self::Eager(e) => e.len(),
-			Self::Extended(v) => v.0.len() + v.1.len(),
+			Self::Extended(v) => v.len(),
 			Self::Range(a, b) => a.abs_diff(*b) as usize + 1,
+			Self::Computed(c) => c.len,
 			Self::Reversed(i) => i.len(),
 			Self::Slice(s) => s.len(),
 		}
@@ -260,20 +567,14 @@ impl ArrValue {
 				}
 			}
 			Self::Eager(vec) => Ok(vec.get(index).cloned()),
-			Self::Extended(v) => {
-				let a_len = v.0.len();
-				if a_len > index {
-					v.0.get(s, index)
-				} else {
-					v.1.get(s, index - a_len)
-				}
-			}
+			Self::Extended(v) => v.get(s, index),
 			Self::Range(a, _) => {
 				if index >= self.len() {
 					return Ok(None);
 				}
 				Ok(Some(Val::Num(((*a as isize) + index as isize) as f64)))
 			}
+			Self::Computed(c) => c.get_lazy(index).map(|v| v.evaluate(s)).transpose(),
 			Self::Reversed(v) => {
 				let len = v.len();
 				if index >= len {
@@ -298,14 +599,7 @@ impl ArrValue {
 				.map(|b| Thunk::evaluated(Val::Num(f64::from(*b)))),
 			Self::Lazy(vec) => vec.get(index).cloned(),
 			Self::Eager(vec) => vec.get(index).cloned().map(Thunk::evaluated),
-			Self::Extended(v) => {
-				let a_len = v.0.len();
-				if a_len > index {
-					v.0.get_lazy(index)
-				} else {
-					v.1.get_lazy(index - a_len)
-				}
-			}
+			Self::Extended(v) => v.get_lazy(index),
 			Self::Range(a, _) => {
 				if index >= self.len() {
 					return None;
@@ -314,6 +608,7 @@ impl ArrValue {
 					((*a as isize) + index as isize) as f64,
 				)))
 			}
+			Self::Computed(c) => c.get_lazy(index),
 			Self::Reversed(v) => {
 				let len = v.len();
 				if index >= len {
@@ -348,7 +643,7 @@ impl ArrValue {
 				Cc::new(out)
 			}
 			Self::Eager(vec) => vec.clone(),
-			Self::Extended(_v) => {
+			Self::Extended(..) | Self::Computed(..) => {
 				let mut out = Vec::with_capacity(self.len());
 				for item in self.iter(s) {
 					out.push(item?);
@@ -357,7 +652,7 @@ impl ArrValue {
 			}
 			Self::Range(a, b) => {
 				let mut out = Vec::with_capacity(self.len());
-				for i in *a..*b {
+				for i in *a..=*b {
 					out.push(Val::Num(f64::from(i)));
 				}
 				Cc::new(out)
@@ -388,9 +683,11 @@ impl ArrValue {
 			Self::Bytes(b) => Ok(Val::Num(f64::from(b[idx]))),
 			Self::Lazy(l) => l[idx].evaluate(s.clone()),
 			Self::Eager(e) => Ok(e[idx].clone()),
-			Self::Extended(..) | Self::Range(..) | Self::Reversed(..) | Self::Slice(..) => {
-				self.get(s.clone(), idx).map(|e| e.expect("idx < len"))
-			}
+			Self::Extended(..)
+			| Self::Range(..)
+			| Self::Computed(..)
+			| Self::Reversed(..)
+			| Self::Slice(..) => self.get(s.clone(), idx).map(|e| e.expect("idx < len")),
 		})
 	}
 
@@ -399,15 +696,32 @@ impl ArrValue {
 			Self::Bytes(b) => Thunk::evaluated(Val::Num(f64::from(b[idx]))),
 			Self::Lazy(l) => l[idx].clone(),
 			Self::Eager(e) => Thunk::evaluated(e[idx].clone()),
-			Self::Slice(..) | Self::Extended(..) | Self::Range(..) | Self::Reversed(..) => {
-				self.get_lazy(idx).expect("idx < len")
-			}
+			Self::Slice(..)
+			| Self::Extended(..)
+			| Self::Range(..)
+			| Self::Computed(..)
+			| Self::Reversed(..) => self.get_lazy(idx).expect("idx < len"),
 		})
 	}
 
+	/// Reversed concatenation stays flat, consisting of reversed parts
 	#[must_use]
 	pub fn reversed(self) -> Self {
-		Self::Reversed(Box::new(self))
+		match self {
+			Self::Extended(v) => {
+				let mut concat = Concat::new();
+				let mut tail = v.tail.items().to_vec();
+				if !tail.is_empty() {
+					tail.reverse();
+					concat.push_part(Self::Lazy(Cc::new(tail)));
+				}
+				for part in v.parts.items().iter().rev() {
+					concat.push_part(part.clone().reversed());
+				}
+				Self::Extended(Cc::new(concat))
+			}
+			v => Self::Reversed(Box::new(v)),
+		}
 	}
 
 	pub fn map(self, s: State, mapper: impl Fn(Val) -> Result<Val>) -> Result<Self> {
@@ -433,10 +747,48 @@ impl ArrValue {
 		Ok(Self::Eager(Cc::new(out)))
 	}
 
+	/// Same as [`Self::filter`], but calls `progress` with number of kept and scanned elements
+	/// after every `every` scanned elements, and once more after the last one.
+	///
+	/// Error returned from `progress` aborts filtering, which may be used to cancel long evaluations
+	///
+	/// # Panics
+	/// If `every` is zero
+	pub fn filter_with_progress(
+		self,
+		s: State,
+		filter: impl Fn(&Val) -> Result<bool>,
+		every: usize,
+		mut progress: impl FnMut(usize, usize) -> Result<()>,
+	) -> Result<Self> {
+		assert!(every != 0, "progress interval should be positive");
+		let len = self.len();
+		let mut out = Vec::with_capacity(len);
+
+		for (i, value) in self.iter(s).enumerate() {
+			let value = value?;
+			if filter(&value)? {
+				out.push(value);
+			}
+			let scanned = i + 1;
+			if scanned % every == 0 || scanned == len {
+				progress(out.len(), scanned)?;
+			}
+		}
+
+		Ok(Self::Eager(Cc::new(out)))
+	}
+
+	/// Checks if both values share the same storage, returning `false` if this can't be cheaply determined.
+	///
+	/// Concatenations are only considered the same, if they share the same list of parts,
+	/// i.e one is a clone of another, equal concatenations built separately are not
 	pub fn ptr_eq(a: &Self, b: &Self) -> bool {
 		match (a, b) {
 			(Self::Lazy(a), Self::Lazy(b)) => Cc::ptr_eq(a, b),
 			(Self::Eager(a), Self::Eager(b)) => Cc::ptr_eq(a, b),
+			(Self::Extended(a), Self::Extended(b)) => Cc::ptr_eq(a, b),
+			(Self::Computed(a), Self::Computed(b)) => Cc::ptr_eq(a, b),
 			_ => false,
 		}
 	}
@@ -540,25 +892,31 @@ impl Val {
 		}
 	}
 
+	/// Converts value to string, as `value + ''` does.
+	/// Spacing of objects and arrays is configured with [`State::set_to_string_spacing`]
 	pub fn to_string(&self, s: State) -> Result<IStr> {
 		Ok(match self {
 			Self::Bool(true) => "true".into(),
 			Self::Bool(false) => "false".into(),
 			Self::Null => "null".into(),
 			Self::Str(s) => s.clone(),
-			v => manifest_json_ex(
-				s,
-				v,
-				&ManifestJsonOptions {
-					padding: "",
-					mtype: ManifestType::ToString,
-					newline: "\n",
-					key_val_sep: ": ",
-					#[cfg(feature = "exp-preserve-order")]
-					preserve_order: false,
-				},
-			)?
-			.into(),
+			v => {
+				let spacing = s.to_string_spacing();
+				manifest_json_ex(
+					s,
+					v,
+					&ManifestJsonOptions {
+						padding: "",
+						mtype: ManifestType::ToString,
+						key_val_sep: &spacing.key_val_sep,
+						item_sep: &spacing.item_sep,
+						empty_padding: &spacing.empty_padding,
+						preserve_order: Some(false),
+						..ManifestJsonOptions::default()
+					},
+				)?
+				.into()
+			}
 		})
 	}
 
@@ -598,6 +956,7 @@ impl Val {
 
 	pub fn manifest(&self, s: State, ty: &ManifestFormat) -> Result<IStr> {
 		Ok(match ty {
+			#[cfg(feature = "yaml")]
 			ManifestFormat::YamlStream(format) => {
 				let arr = match self {
 					Self::Arr(a) => a,
@@ -606,7 +965,9 @@ impl Val {
 				let mut out = String::new();
 
 				match format as &ManifestFormat {
-					ManifestFormat::YamlStream(_) => throw!(StreamManifestOutputCannotBeRecursed),
+					ManifestFormat::YamlStream(_) | ManifestFormat::Jsonl { .. } => {
+						throw!(StreamManifestOutputCannotBeRecursed)
+					}
 					ManifestFormat::String => throw!(StreamManifestCannotNestString),
 					_ => {}
 				};
@@ -622,6 +983,7 @@ impl Val {
 
 				out.into()
 			}
+			#[cfg(feature = "yaml")]
 			ManifestFormat::Yaml {
 				padding,
 				#[cfg(feature = "exp-preserve-order")]
@@ -636,12 +998,82 @@ impl Val {
 				padding,
 				#[cfg(feature = "exp-preserve-order")]
 				preserve_order,
-			} => self.to_json(
+			} => self
+				.manifest_json(
+					s.clone(),
+					padding,
+					if s.highlight_output() {
+						&AnsiStyler
+					} else {
+						&NoStyle
+					},
+					#[cfg(feature = "exp-preserve-order")]
+					*preserve_order,
+				)?
+				.into(),
+			ManifestFormat::Jsonc {
+				padding,
+				#[cfg(feature = "exp-preserve-order")]
+				preserve_order,
+			} => Self::with_json_options(
+				padding,
+				&NoStyle,
+				#[cfg(feature = "exp-preserve-order")]
+				*preserve_order,
+				|options| {
+					manifest_json_ex(
+						s,
+						self,
+						&ManifestJsonOptions {
+							comments: true,
+							..*options
+						},
+					)
+				},
+			)?
+			.into(),
+			ManifestFormat::Jsonl {
+				trailing_newline,
+				#[cfg(feature = "exp-preserve-order")]
+				preserve_order,
+			} => self.to_jsonl(
+				s,
+				*trailing_newline,
+				#[cfg(feature = "exp-preserve-order")]
+				*preserve_order,
+			)?,
+			ManifestFormat::Toml {
+				padding,
+				#[cfg(feature = "exp-preserve-order")]
+				preserve_order,
+			} => self.to_toml(
 				s,
 				*padding,
 				#[cfg(feature = "exp-preserve-order")]
 				*preserve_order,
 			)?,
+			ManifestFormat::Xml {
+				indent,
+				self_closing,
+				#[cfg(feature = "exp-preserve-order")]
+				preserve_order,
+			} => self.to_xml(
+				s,
+				indent,
+				*self_closing,
+				#[cfg(feature = "exp-preserve-order")]
+				*preserve_order,
+			)?,
+			ManifestFormat::Csv {
+				headers,
+				#[cfg(feature = "exp-preserve-order")]
+				preserve_order,
+			} => self.to_csv(
+				s,
+				headers.as_deref(),
+				#[cfg(feature = "exp-preserve-order")]
+				*preserve_order,
+			)?,
 			ManifestFormat::ToString => self.to_string(s)?,
 			ManifestFormat::String => match self {
 				Self::Str(s) => s.clone(),
@@ -650,47 +1082,261 @@ impl Val {
 		})
 	}
 
+	/// Same as [`Self::manifest`], but output is written to `out` while it is produced,
+	/// without keeping the whole manifested value in memory.
+	///
+	/// Only json, jsonc and yaml are streamed, other formats are written after manifestification.
+	/// On error, part of the output may already be written, and the writer contents should be discarded
+	pub fn manifest_to(
+		&self,
+		s: State,
+		ty: &ManifestFormat,
+		out: &mut dyn io::Write,
+	) -> Result<()> {
+		match ty {
+			#[cfg(feature = "yaml")]
+			ManifestFormat::Yaml {
+				padding,
+				#[cfg(feature = "exp-preserve-order")]
+				preserve_order,
+			} => Self::with_yaml_options(
+				*padding,
+				#[cfg(feature = "exp-preserve-order")]
+				*preserve_order,
+				|options| manifest_yaml_ex_to(s, self, out, options),
+			),
+			ManifestFormat::Json {
+				padding,
+				#[cfg(feature = "exp-preserve-order")]
+				preserve_order,
+			} => Self::with_json_options(
+				padding,
+				if s.highlight_output() {
+					&AnsiStyler
+				} else {
+					&NoStyle
+				},
+				#[cfg(feature = "exp-preserve-order")]
+				*preserve_order,
+				|options| manifest_json_ex_to(s, self, out, options),
+			),
+			ManifestFormat::Jsonc {
+				padding,
+				#[cfg(feature = "exp-preserve-order")]
+				preserve_order,
+			} => Self::with_json_options(
+				padding,
+				&NoStyle,
+				#[cfg(feature = "exp-preserve-order")]
+				*preserve_order,
+				|options| {
+					manifest_json_ex_to(
+						s,
+						self,
+						out,
+						&ManifestJsonOptions {
+							comments: true,
+							..*options
+						},
+					)
+				},
+			),
+			_ => {
+				let manifested = self.manifest(s, ty)?;
+				out.write_all(manifested.as_bytes()).map_err(|e| {
+					RuntimeError(format!("failed to write manifest output: {}", e).into())
+				})?;
+				Ok(())
+			}
+		}
+	}
+
+	/// Options of json manifestification, used for output
+	fn with_json_options<T>(
+		padding: &str,
+		styler: &dyn JsonStyler,
+		#[cfg(feature = "exp-preserve-order")] preserve_order: bool,
+		f: impl FnOnce(&ManifestJsonOptions<'_>) -> T,
+	) -> T {
+		let minify = padding.is_empty();
+		f(&ManifestJsonOptions {
+			padding,
+			mtype: if minify {
+				ManifestType::Minify
+			} else {
+				ManifestType::Manifest
+			},
+			key_val_sep: if minify { ":" } else { ": " },
+			#[cfg(feature = "exp-preserve-order")]
+			preserve_order: Some(preserve_order),
+			styler,
+			..ManifestJsonOptions::default()
+		})
+	}
+
+	fn manifest_json(
+		&self,
+		s: State,
+		padding: &str,
+		styler: &dyn JsonStyler,
+		#[cfg(feature = "exp-preserve-order")] preserve_order: bool,
+	) -> Result<String> {
+		Self::with_json_options(
+			padding,
+			styler,
+			#[cfg(feature = "exp-preserve-order")]
+			preserve_order,
+			|options| manifest_json_ex(s, self, options),
+		)
+	}
+
 	/// For manifestification
 	pub fn to_json(
 		&self,
 		s: State,
-		padding: usize,
+		padding: &str,
+		#[cfg(feature = "exp-preserve-order")] preserve_order: bool,
+	) -> Result<IStr> {
+		self.manifest_json(
+			s,
+			padding,
+			&NoStyle,
+			#[cfg(feature = "exp-preserve-order")]
+			preserve_order,
+		)
+		.map(Into::into)
+	}
+
+	/// Manifests every element of array as minified json on its own line
+	pub fn to_jsonl(
+		&self,
+		s: State,
+		trailing_newline: bool,
 		#[cfg(feature = "exp-preserve-order")] preserve_order: bool,
 	) -> Result<IStr> {
+		let arr = match self {
+			Self::Arr(a) => a,
+			_ => throw!(StreamManifestOutputIsNotAArray),
+		};
+		let mut out = String::new();
+		for (i, v) in arr.iter(s.clone()).enumerate() {
+			if i != 0 {
+				out.push('\n');
+			}
+			let line = s.push_description(
+				|| format!("elem <{}> manifestification", i),
+				|| {
+					v?.manifest_json(
+						s.clone(),
+						"",
+						&NoStyle,
+						#[cfg(feature = "exp-preserve-order")]
+						preserve_order,
+					)
+				},
+			)?;
+			out.push_str(&line);
+		}
+		if trailing_newline && !arr.is_empty() {
+			out.push('\n');
+		}
+		Ok(out.into())
+	}
+
+	/// Same as [`Self::to_json`], but output is interned as bytes,
+	/// for consumers which pass manifested value further as binary data.
+	/// Interning copies the manifested output
+	pub fn to_json_bytes(
+		&self,
+		s: State,
+		padding: &str,
+		#[cfg(feature = "exp-preserve-order")] preserve_order: bool,
+	) -> Result<IBytes> {
+		self.manifest_json(
+			s,
+			padding,
+			&NoStyle,
+			#[cfg(feature = "exp-preserve-order")]
+			preserve_order,
+		)
+		.map(|v| v.as_bytes().into())
+	}
+
+	/// Calls `std.manifestJson`
+	pub fn to_std_json(
+		&self,
+		s: State,
+		padding: &str,
+		#[cfg(feature = "exp-preserve-order")] preserve_order: bool,
+	) -> Result<Rc<str>> {
 		manifest_json_ex(
 			s,
 			self,
 			&ManifestJsonOptions {
-				padding: &" ".repeat(padding),
-				mtype: if padding == 0 {
-					ManifestType::Minify
-				} else {
-					ManifestType::Manifest
-				},
-				newline: "\n",
-				key_val_sep: ": ",
+				padding,
 				#[cfg(feature = "exp-preserve-order")]
-				preserve_order,
+				preserve_order: Some(preserve_order),
+				..ManifestJsonOptions::default()
 			},
 		)
 		.map(Into::into)
 	}
 
-	/// Calls `std.manifestJson`
-	pub fn to_std_json(
+	/// Options of yaml manifestification, used for output
+	#[cfg(feature = "yaml")]
+	fn with_yaml_options<T>(
+		padding: usize,
+		#[cfg(feature = "exp-preserve-order")] preserve_order: bool,
+		f: impl FnOnce(&ManifestYamlOptions<'_>) -> T,
+	) -> T {
+		let padding = &" ".repeat(padding);
+		f(&ManifestYamlOptions {
+			padding,
+			arr_element_padding: padding,
+			quote_keys: false,
+			functions: &FunctionManifest::Error,
+			comments: false,
+			escape_c1: true,
+			validate_field_names: false,
+			#[cfg(feature = "exp-preserve-order")]
+			preserve_order: Some(preserve_order),
+			#[cfg(not(feature = "exp-preserve-order"))]
+			preserve_order: None,
+			binary: false,
+		})
+	}
+
+	#[cfg(feature = "yaml")]
+	pub fn to_yaml(
 		&self,
 		s: State,
 		padding: usize,
 		#[cfg(feature = "exp-preserve-order")] preserve_order: bool,
-	) -> Result<Rc<str>> {
-		manifest_json_ex(
+	) -> Result<IStr> {
+		Self::with_yaml_options(
+			padding,
+			#[cfg(feature = "exp-preserve-order")]
+			preserve_order,
+			|options| manifest_yaml_ex(s, self, options),
+		)
+		.map(Into::into)
+	}
+
+	pub fn to_toml(
+		&self,
+		s: State,
+		padding: usize,
+		#[cfg(feature = "exp-preserve-order")] preserve_order: bool,
+	) -> Result<IStr> {
+		manifest_toml_ex(
 			s,
 			self,
-			&ManifestJsonOptions {
+			&ManifestTomlOptions {
 				padding: &" ".repeat(padding),
-				mtype: ManifestType::Std,
-				newline: "\n",
-				key_val_sep: ": ",
+				inline_empty_tables: false,
+				inline_table_max_fields: None,
+				inline_table_max_depth: 0,
+				datetimes: false,
 				#[cfg(feature = "exp-preserve-order")]
 				preserve_order,
 			},
@@ -698,26 +1344,112 @@ impl Val {
 		.map(Into::into)
 	}
 
-	pub fn to_yaml(
+	pub fn to_xml(
 		&self,
 		s: State,
-		padding: usize,
+		indent: &str,
+		self_closing: bool,
 		#[cfg(feature = "exp-preserve-order")] preserve_order: bool,
 	) -> Result<IStr> {
-		let padding = &" ".repeat(padding);
-		manifest_yaml_ex(
+		manifest_xml_jsonml(
 			s,
 			self,
-			&ManifestYamlOptions {
-				padding,
-				arr_element_padding: padding,
-				quote_keys: false,
+			&ManifestXmlOptions {
+				self_closing,
+				indent: Some(indent).filter(|i| !i.is_empty()),
+				#[cfg(feature = "exp-preserve-order")]
+				preserve_order,
+			},
+		)
+		.map(Into::into)
+	}
+
+	pub fn to_csv(
+		&self,
+		s: State,
+		headers: Option<&[IStr]>,
+		#[cfg(feature = "exp-preserve-order")] preserve_order: bool,
+	) -> Result<IStr> {
+		manifest_csv(
+			s,
+			self,
+			&ManifestCsvOptions {
+				headers,
 				#[cfg(feature = "exp-preserve-order")]
 				preserve_order,
 			},
 		)
 		.map(Into::into)
 	}
+
+	/// Same as [`equals`], but objects are only considered equal if their fields
+	/// were also defined in the same order, which is useful to check manifested output ordering.
+	///
+	/// `==` and `std.equals` ignore field order
+	#[cfg(feature = "exp-preserve-order")]
+	pub fn equals_ordered(&self, s: State, other: &Self) -> Result<bool> {
+		equals_inner(s, self, other, true)
+	}
+
+	/// Recursively forces all lazy parts of the value, producing tree of eager arrays and objects,
+	/// which has no pending thunks, and can be cached between evaluations.
+	///
+	/// Only visible fields are kept, same as in manifested output, hidden fields are dropped
+	/// without being evaluated. Object assertions are checked.
+	/// With `exp-preserve-order`, materialized objects keep field order of the original ones.
+	///
+	/// Fails on cyclic values, i.e `local a = { b: a }; a`
+	pub fn materialize(&self, s: State) -> Result<Self> {
+		self.materialize_inner(s, &mut Vec::new())
+	}
+	fn materialize_inner(&self, s: State, path: &mut Vec<Self>) -> Result<Self> {
+		if path.iter().any(|v| match (v, self) {
+			(Self::Arr(a), Self::Arr(b)) => ArrValue::ptr_eq(a, b),
+			(Self::Obj(a), Self::Obj(b)) => ObjValue::ptr_eq(a, b),
+			_ => false,
+		}) {
+			throw!(MaterializeCycle);
+		}
+		Ok(match self {
+			Self::Arr(arr) => {
+				path.push(self.clone());
+				let mut out = Vec::with_capacity(arr.len());
+				for (i, item) in arr.iter(s.clone()).enumerate() {
+					let item = item?;
+					out.push(s.push_description(
+						|| format!("elem <{}> materialization", i),
+						|| item.materialize_inner(s.clone(), path),
+					)?);
+				}
+				path.pop();
+				Self::Arr(out.into())
+			}
+			Self::Obj(obj) => {
+				path.push(self.clone());
+				obj.run_assertions(s.clone())?;
+				let fields = obj.fields(
+					#[cfg(feature = "exp-preserve-order")]
+					true,
+				);
+				let mut builder = ObjValueBuilder::with_capacity(fields.len());
+				for name in fields {
+					let value = s.push_description(
+						|| format!("field <{}> materialization", name.clone()),
+						|| {
+							obj.get(s.clone(), name.clone())?
+								.expect("field exists")
+								.materialize_inner(s.clone(), path)
+						},
+					)?;
+					builder.member(name).value(s.clone(), value)?;
+				}
+				path.pop();
+				Self::Obj(builder.build())
+			}
+			v => v.clone(),
+		})
+	}
+
 	pub fn into_indexable(self) -> Result<IndexableVal> {
 		Ok(match self {
 			Val::Str(s) => IndexableVal::Str(s),
@@ -727,6 +1459,21 @@ impl Val {
 	}
 }
 
+/// Returns whole number represented by `n`, tolerating error accumulated by float arithmetic,
+/// so i.e `(0.1 + 0.2) * 10` is accepted as `3`.
+///
+/// Number is rounded to the nearest integer if it is within relative [`f64::EPSILON`] from it,
+/// otherwise it is considered fractional, and `None` is returned.
+/// Used everywhere, where numbers are treated as integers: for indexing, slicing and integer arguments
+pub fn whole_number(n: f64) -> Option<f64> {
+	let rounded = n.round();
+	if (n - rounded).abs() <= f64::EPSILON * rounded.abs().max(1.0) {
+		Some(rounded)
+	} else {
+		None
+	}
+}
+
 const fn is_function_like(val: &Val) -> bool {
 	matches!(val, Val::Func(_))
 }
@@ -751,8 +1498,71 @@ pub fn primitive_equals(val_a: &Val, val_b: &Val) -> Result<bool> {
 	})
 }
 
+/// Feeds structural hash of value into `hasher`, consistent with [`equals`]:
+/// equal values always have equal hashes.
+///
+/// Hash doesn't depend on object field order or on interner state, so with deterministic hasher
+/// it is stable between runs.
+///
+/// [`equals`] compares numbers with absolute [`f64::EPSILON`], so distinct numbers may be equal
+/// only if spacing between adjacent floats is not larger than epsilon, which is the case for
+/// `[-2, 2]`. All numbers in this range share the same hash, and `-0.0` is hashed same as `0.0`.
+/// Outside of it, equal numbers are bit-identical, and their bits are hashed.
+/// Fails on functions, as they have no equality
+pub fn hash_val(s: State, val: &Val, hasher: &mut impl Hasher) -> Result<()> {
+	std::mem::discriminant(val).hash(hasher);
+	match val {
+		Val::Bool(v) => v.hash(hasher),
+		Val::Null => {}
+		Val::Str(v) => (v as &str).hash(hasher),
+		Val::Num(n) => {
+			if n.abs() > 2.0 {
+				n.to_bits().hash(hasher);
+			}
+		}
+		Val::Arr(a) => {
+			a.len().hash(hasher);
+			for item in a.iter(s.clone()) {
+				hash_val(s.clone(), &item?, hasher)?;
+			}
+		}
+		Val::Obj(o) => {
+			let fields = o.fields(
+				#[cfg(feature = "exp-preserve-order")]
+				false,
+			);
+			fields.len().hash(hasher);
+			for field in fields {
+				(&field as &str).hash(hasher);
+				hash_val(
+					s.clone(),
+					&o.get(s.clone(), field)?.expect("field exists"),
+					hasher,
+				)?;
+			}
+		}
+		Val::Func(_) => throw!(RuntimeError("cannot hash functions".into())),
+	}
+	Ok(())
+}
+
 /// Native implementation of `std.equals`
 pub fn equals(s: State, val_a: &Val, val_b: &Val) -> Result<bool> {
+	equals_inner(
+		s,
+		val_a,
+		val_b,
+		#[cfg(feature = "exp-preserve-order")]
+		false,
+	)
+}
+
+fn equals_inner(
+	s: State,
+	val_a: &Val,
+	val_b: &Val,
+	#[cfg(feature = "exp-preserve-order")] preserve_order: bool,
+) -> Result<bool> {
 	if val_a.value_type() != val_b.value_type() {
 		return Ok(false);
 	}
@@ -765,7 +1575,13 @@ pub fn equals(s: State, val_a: &Val, val_b: &Val) -> Result<bool> {
 				return Ok(false);
 			}
 			for (a, b) in a.iter(s.clone()).zip(b.iter(s.clone())) {
-				if !equals(s.clone(), &a?, &b?)? {
+				if !equals_inner(
+					s.clone(),
+					&a?,
+					&b?,
+					#[cfg(feature = "exp-preserve-order")]
+					preserve_order,
+				)? {
 					return Ok(false);
 				}
 			}
@@ -777,20 +1593,22 @@ pub fn equals(s: State, val_a: &Val, val_b: &Val) -> Result<bool> {
 			}
 			let fields = a.fields(
 				#[cfg(feature = "exp-preserve-order")]
-				false,
+				preserve_order,
 			);
 			if fields
 				!= b.fields(
 					#[cfg(feature = "exp-preserve-order")]
-					false,
+					preserve_order,
 				) {
 				return Ok(false);
 			}
 			for field in fields {
-				if !equals(
+				if !equals_inner(
 					s.clone(),
 					&a.get(s.clone(), field.clone())?.expect("field exists"),
 					&b.get(s.clone(), field)?.expect("field exists"),
+					#[cfg(feature = "exp-preserve-order")]
+					preserve_order,
 				)? {
 					return Ok(false);
 				}
@@ -3,7 +3,7 @@ mod common;
 use jrsonnet_evaluator::{
 	error::Result,
 	function::{builtin, builtin::Builtin, CallLocation, FuncVal},
-	tb,
+	tb, throw_runtime,
 	typed::Typed,
 	State, Val,
 };
@@ -59,6 +59,35 @@ fn call_from_code() -> Result<()> {
 	Ok(())
 }
 
+#[test]
+fn native_arity() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+	s.add_native("nativeAdd".into(), Cc::new(tb!(native_add {})));
+
+	let v = s.evaluate_snippet(
+		"snip".to_owned(),
+		"
+            assert std.native('nativeAdd', 2)(1, 2) == 3;
+            assert std.native('missing', 2) == null;
+            assert std.nativeNames() == ['nativeAdd'];
+            null
+        "
+		.into(),
+	)?;
+	ensure_val_eq!(s, v, Val::Null);
+
+	let e = match s.evaluate_snippet("snip".to_owned(), "std.native('nativeAdd', 3)".into()) {
+		Ok(_) => throw_runtime!("arity mismatch should fail"),
+		Err(e) => e,
+	};
+	ensure_eq!(
+		e.error().to_string(),
+		"runtime error: native function nativeAdd has 2 parameters, expected 3".to_owned()
+	);
+	Ok(())
+}
+
 #[builtin(fields(
     a: u32
 ))]
@@ -32,13 +32,13 @@ macro_rules! ensure_val_eq {
 				"assertion failed: a != b\na={:#?}\nb={:#?}",
 				$a.to_json(
 					$s.clone(),
-					2,
+					"  ",
 					#[cfg(feature = "exp-preserve-order")]
 					false
 				)?,
 				$b.to_json(
 					$s.clone(),
-					2,
+					"  ",
 					#[cfg(feature = "exp-preserve-order")]
 					false
 				)?,
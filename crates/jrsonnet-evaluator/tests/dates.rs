@@ -0,0 +1,22 @@
+#![cfg(feature = "dates")]
+
+mod common;
+
+use jrsonnet_evaluator::{error::Result, State, Val};
+
+#[test]
+fn date_now() -> Result<()> {
+	use jrsonnet_evaluator::chrono::DateTime;
+
+	let s = State::default();
+	s.with_stdlib();
+	s.set_now(Some(
+		DateTime::parse_from_rfc3339("2022-05-01T10:00:00+02:00").unwrap(),
+	));
+	let v = s.evaluate_snippet(
+		"snip".to_owned(),
+		"std.dateAdd(std.dateNow(), 86400)".into(),
+	)?;
+	ensure_val_eq!(s, v, Val::Str("2022-05-02T10:00:00+02:00".into()));
+	Ok(())
+}
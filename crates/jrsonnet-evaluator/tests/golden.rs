@@ -4,7 +4,7 @@ use std::{
 };
 
 use jrsonnet_evaluator::{
-	trace::{CompactFormat, PathResolver},
+	trace::{CompactFormat, FrameFilter, PathResolver},
 	FileImportResolver, State,
 };
 
@@ -15,6 +15,8 @@ fn run(root: &Path, file: &Path) -> String {
 	s.set_trace_format(Box::new(CompactFormat {
 		resolver: PathResolver::Relative(root.to_owned()),
 		padding: 3,
+		separator: " ".to_owned(),
+		filter: FrameFilter::None,
 	}));
 	s.with_stdlib();
 	common::with_test(&s);
@@ -26,7 +28,7 @@ fn run(root: &Path, file: &Path) -> String {
 	};
 	match v.to_json(
 		s.clone(),
-		3,
+		"   ",
 		#[cfg(feature = "exp-preserve-order")]
 		false,
 	) {
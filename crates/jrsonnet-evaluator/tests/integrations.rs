@@ -0,0 +1,64 @@
+mod common;
+
+use jrsonnet_evaluator::{error::Result, throw_runtime, State, Val};
+
+#[test]
+fn stream_json_events() -> Result<()> {
+	use jrsonnet_evaluator::integrations::stream::{stream_json, ParseEvent};
+
+	let mut events = vec![];
+	stream_json(
+		r#"{"a": [1, "b", null], "c": {"d": true}}"#.as_bytes(),
+		|e| {
+			events.push(format!("{:?}", e));
+			Ok(())
+		},
+	)?;
+	ensure_eq!(
+		events.join(" "),
+		r#"ObjectStart Key("a") ArrayStart Num(1.0) Str("b") Null ArrayEnd Key("c") ObjectStart Key("d") Bool(true) ObjectEnd ObjectEnd"#
+	);
+
+	let mut seen = 0;
+	let e = match stream_json("[1, 2, 3]".as_bytes(), |e| {
+		if e == ParseEvent::Num(2.0) {
+			throw_runtime!("enough");
+		}
+		seen += 1;
+		Ok(())
+	}) {
+		Ok(_) => throw_runtime!("handler error should abort parsing"),
+		Err(e) => e,
+	};
+	ensure_eq!(e.error().to_string(), "runtime error: enough".to_owned());
+	ensure_eq!(seen, 2);
+
+	Ok(())
+}
+
+#[test]
+fn serde_number_conversion() -> Result<()> {
+	use jrsonnet_evaluator::typed::Typed;
+	use serde_json::Value;
+
+	let s = State::default();
+
+	let zero = Value::from_untyped(Val::Num(-0.0), s.clone())?;
+	ensure_eq!(zero.to_string(), "-0.0".to_owned());
+	match Value::into_untyped(zero, s.clone())? {
+		Val::Num(n) => ensure!(n == 0.0 && n.is_sign_negative()),
+		_ => throw_runtime!("number expected"),
+	}
+	ensure_eq!(
+		Value::from_untyped(Val::Num(0.0), s.clone())?.to_string(),
+		"0".to_owned()
+	);
+
+	// Numbers which are equal with epsilon are still converted exactly
+	let a = Value::from_untyped(Val::Num(0.1 + 0.2), s.clone())?;
+	let b = Value::from_untyped(Val::Num(0.3), s)?;
+	ensure_eq!(a.to_string(), "0.30000000000000004".to_owned());
+	ensure_eq!(b.to_string(), "0.3".to_owned());
+
+	Ok(())
+}
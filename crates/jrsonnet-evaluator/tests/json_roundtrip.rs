@@ -0,0 +1,18 @@
+#![cfg(feature = "test-util")]
+
+use jrsonnet_evaluator::{error::Result, test_util::assert_json_roundtrip, State};
+
+#[test]
+fn json_roundtrip() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+
+	let v = s.evaluate_snippet(
+		"snip".to_owned(),
+		r#"{a: [1, 2.5, -0.125, 1e21], b: "esc\"aped\n\u0001", c: {d: null, e: true}, f: []}"#
+			.into(),
+	)?;
+	assert_json_roundtrip(s, &v)?;
+
+	Ok(())
+}
@@ -0,0 +1,440 @@
+mod common;
+
+use jrsonnet_evaluator::{error::Result, throw_runtime, ManifestFormat, State};
+
+#[test]
+fn json_bytes() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+
+	let v = s.evaluate_snippet("snip".to_owned(), "{a: ['ы', 1]}".into())?;
+	let bytes = v.to_json_bytes(
+		s.clone(),
+		"",
+		#[cfg(feature = "exp-preserve-order")]
+		false,
+	)?;
+	let json = v.to_json(
+		s,
+		"",
+		#[cfg(feature = "exp-preserve-order")]
+		false,
+	)?;
+	ensure_eq!(&bytes as &[u8], json.as_bytes());
+
+	Ok(())
+}
+
+#[test]
+fn json_tab_indent() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+
+	let v = s.evaluate_snippet("snip".to_owned(), "{a: [1]}".into())?;
+	let json = v.to_json(
+		s,
+		"\t",
+		#[cfg(feature = "exp-preserve-order")]
+		false,
+	)?;
+	ensure_eq!(json.to_string(), "{\n\t\"a\": [\n\t\t1\n\t]\n}".to_owned());
+
+	Ok(())
+}
+
+#[test]
+fn jsonl_element_errors() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+	s.set_manifest_format(ManifestFormat::Jsonl {
+		trailing_newline: false,
+		#[cfg(feature = "exp-preserve-order")]
+		preserve_order: false,
+	});
+
+	let v = s.evaluate_snippet("snip".to_owned(), "[{a: 1}, [2]]".into())?;
+	ensure_eq!(s.manifest(v)?.to_string(), "{\"a\":1}\n[2]".to_owned());
+
+	let v = s.evaluate_snippet("snip".to_owned(), "[1, {a: error 'bad'}]".into())?;
+	let e = match s.manifest(v) {
+		Ok(_) => throw_runtime!("element error should be reported"),
+		Err(e) => e,
+	};
+	ensure!(s.stringify_err(&e).contains("elem <1> manifestification"));
+
+	let v = s.evaluate_snippet("snip".to_owned(), "{a: 1}".into())?;
+	let e = match s.manifest(v) {
+		Ok(_) => throw_runtime!("only arrays can be manifested as jsonl"),
+		Err(e) => e,
+	};
+	ensure_eq!(
+		e.error().to_string(),
+		"stream manifest output is not an array".to_owned()
+	);
+
+	Ok(())
+}
+
+#[test]
+fn jsonl_stream() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+	s.set_manifest_format(ManifestFormat::Jsonl {
+		trailing_newline: true,
+		#[cfg(feature = "exp-preserve-order")]
+		preserve_order: false,
+	});
+
+	let v = s.evaluate_snippet("snip".to_owned(), "[{a: [1, 2]}, 'b']".into())?;
+	ensure_eq!(
+		s.manifest(v)?.to_string(),
+		"{\"a\":[1,2]}\n\"b\"\n".to_owned()
+	);
+	let v = s.evaluate_snippet("snip".to_owned(), "[]".into())?;
+	ensure_eq!(s.manifest(v)?.to_string(), String::new());
+
+	#[cfg(feature = "yaml")]
+	{
+		s.set_manifest_format(ManifestFormat::YamlStream(Box::new(s.manifest_format())));
+		let v = s.evaluate_snippet("snip".to_owned(), "[[1]]".into())?;
+		let e = match s.manifest(v) {
+			Ok(_) => throw_runtime!("streams can't be nested"),
+			Err(e) => e,
+		};
+		ensure_eq!(
+			e.error().to_string(),
+			"cant recurse stream manifest".to_owned()
+		);
+	}
+
+	Ok(())
+}
+
+#[test]
+fn xml_manifest() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+	s.set_manifest_format(ManifestFormat::Xml {
+		indent: "  ".to_owned(),
+		self_closing: true,
+		#[cfg(feature = "exp-preserve-order")]
+		preserve_order: false,
+	});
+
+	let v = s.evaluate_snippet(
+		"snip".to_owned(),
+		"['feed', {version: 2}, ['title', 'a < b & c'], ['link', {href: '/?a=1&b=\"2\"'}]]".into(),
+	)?;
+	ensure_eq!(
+		s.manifest(v)?.to_string(),
+		"<feed version=\"2\">\n  <title>a &lt; b &amp; c</title>\n  <link href=\"/?a=1&amp;b=&quot;2&quot;\"/>\n</feed>"
+			.to_owned()
+	);
+
+	Ok(())
+}
+
+#[test]
+fn csv_manifest() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+	let rows = s.evaluate_snippet(
+		"snip".to_owned(),
+		r#"[
+			{name: 'a,b', n: 1, note: 'say "hi"'},
+			{name: 'multi\nline', n: null},
+			{name: 'c', n: 2.5, note: true},
+		]"#
+		.into(),
+	)?;
+
+	s.set_manifest_format(ManifestFormat::Csv {
+		headers: None,
+		#[cfg(feature = "exp-preserve-order")]
+		preserve_order: false,
+	});
+	ensure_eq!(
+		s.manifest(rows.clone())?.to_string(),
+		"n,name,note\n1,\"a,b\",\"say \"\"hi\"\"\"\n,\"multi\nline\",\n2.5,c,true\n".to_owned()
+	);
+
+	s.set_manifest_format(ManifestFormat::Csv {
+		headers: Some(vec!["name".into(), "missing".into()]),
+		#[cfg(feature = "exp-preserve-order")]
+		preserve_order: false,
+	});
+	ensure_eq!(
+		s.manifest(rows)?.to_string(),
+		"name,missing\n\"a,b\",\n\"multi\nline\",\nc,\n".to_owned()
+	);
+
+	s.set_manifest_format(ManifestFormat::Csv {
+		headers: None,
+		#[cfg(feature = "exp-preserve-order")]
+		preserve_order: false,
+	});
+	let nested = s.evaluate_snippet("snip".to_owned(), "[{a: 1}, {a: [1]}]".into())?;
+	match s.manifest(nested) {
+		Ok(_) => throw_runtime!("nested values can't be written as csv"),
+		Err(e) => ensure_eq!(
+			e.error().to_string(),
+			"runtime error: field a of CSV row 1 should be a primitive, got array".to_owned()
+		),
+	}
+
+	Ok(())
+}
+
+#[test]
+fn toml_manifest() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+	s.set_manifest_format(ManifestFormat::Toml {
+		padding: 2,
+		#[cfg(feature = "exp-preserve-order")]
+		preserve_order: false,
+	});
+
+	let v = s.evaluate_snippet(
+		"snip".to_owned(),
+		r#"{
+			name: 'a "quoted"\tstring\u0001',
+			version: 1,
+			ratio: 0.5,
+			'key with spaces': true,
+			list: [1, 'two', {inline: []}],
+			deps: {serde: {version: '1.0', features: ['derive']}, 'a.b': {}},
+			bin: [{name: 'x'}, {name: 'y', extra: {path: 'src/y.rs'}}],
+		}"#
+		.into(),
+	)?;
+	ensure_eq!(
+		s.manifest(v)?.to_string(),
+		r#""key with spaces" = true
+list = [
+  1,
+  "two",
+  { inline = [] }
+]
+name = "a \"quoted\"\tstring\u0001"
+ratio = 0.5
+version = 1
+
+[[bin]]
+  name = "x"
+
+[[bin]]
+  name = "y"
+
+  [bin.extra]
+    path = "src/y.rs"
+
+[deps]
+  [deps."a.b"]
+
+  [deps.serde]
+    features = [
+      "derive"
+    ]
+    version = "1.0""#
+			.to_owned()
+	);
+
+	let v = s.evaluate_snippet("snip".to_owned(), "[1]".into())?;
+	let e = match s.manifest(v) {
+		Ok(_) => throw_runtime!("only objects can be manifested as toml"),
+		Err(e) => e,
+	};
+	ensure_eq!(
+		e.error().to_string(),
+		"runtime error: toml manifest output should be an object, got array".to_owned()
+	);
+
+	Ok(())
+}
+
+#[test]
+fn manifest_to_writer() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+
+	let v = s.evaluate_snippet(
+		"snip".to_owned(),
+		"{a: [{b: std.repeat('x', 100), c: i} for i in std.range(0, 1000)], d: null}".into(),
+	)?;
+	for format in [
+		ManifestFormat::Json {
+			padding: "  ".to_owned(),
+			#[cfg(feature = "exp-preserve-order")]
+			preserve_order: false,
+		},
+		#[cfg(feature = "yaml")]
+		ManifestFormat::Yaml {
+			padding: 2,
+			#[cfg(feature = "exp-preserve-order")]
+			preserve_order: false,
+		},
+		ManifestFormat::ToString,
+	] {
+		let mut out = Vec::new();
+		v.manifest_to(s.clone(), &format, &mut out)?;
+		ensure_eq!(
+			String::from_utf8(out).expect("utf8"),
+			v.manifest(s.clone(), &format)?.to_string()
+		);
+	}
+
+	let v = s.evaluate_snippet(
+		"snip".to_owned(),
+		"[std.repeat('x', 1000) for _ in std.range(0, 100)] + [error 'fail']".into(),
+	)?;
+	let mut out = Vec::new();
+	let e = match v.manifest_to(s.clone(), &s.manifest_format(), &mut out) {
+		Ok(_) => throw_runtime!("error should be thrown"),
+		Err(e) => e,
+	};
+	ensure_eq!(e.error().to_string(), "runtime error: fail".to_owned());
+	// Elements before error were already written
+	ensure!(!out.is_empty());
+
+	Ok(())
+}
+
+#[test]
+fn to_string_spacing_setting() -> Result<()> {
+	use jrsonnet_evaluator::ToStringSpacing;
+
+	let s = State::default();
+	s.with_stdlib();
+	let stringify = |code: &str| -> Result<String> {
+		let v = s.evaluate_snippet("snip".to_owned(), code.to_owned())?;
+		Ok(v.to_string(s.clone())?.to_string())
+	};
+	let code = "{a: 1, b: [1, 2, []], c: {}} + ''";
+
+	ensure_eq!(s.to_string_spacing(), ToStringSpacing::default());
+	ensure_eq!(stringify(code)?, r#"{"a": 1, "b": [1, 2, [ ]], "c": { }}"#);
+
+	s.set_to_string_spacing(ToStringSpacing {
+		key_val_sep: ":".into(),
+		..ToStringSpacing::default()
+	});
+	ensure_eq!(stringify(code)?, r#"{"a":1, "b":[1, 2, [ ]], "c":{ }}"#);
+
+	s.set_to_string_spacing(ToStringSpacing {
+		key_val_sep: ":".into(),
+		item_sep: "".into(),
+		empty_padding: "".into(),
+	});
+	ensure_eq!(stringify(code)?, r#"{"a":1,"b":[1,2,[]],"c":{}}"#);
+	ensure_eq!(
+		stringify("std.toString([{a: 1, b: 2}, 'x'])")?,
+		r#"[{"a":1,"b":2},"x"]"#
+	);
+	ensure_eq!(stringify("{a: {b: [1]}}")?, r#"{"a":{"b":[1]}}"#);
+
+	Ok(())
+}
+
+#[test]
+fn jsonc_manifest() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+	s.set_manifest_format(ManifestFormat::Jsonc {
+		padding: "  ".to_owned(),
+		#[cfg(feature = "exp-preserve-order")]
+		preserve_order: false,
+	});
+
+	let v = s.evaluate_snippet("snip".to_owned(), "{a: [1], __comment_a__: 'list'}".into())?;
+	let expected = "{\n  // list\n  \"a\": [\n    1\n  ]\n}";
+	ensure_eq!(s.manifest(v.clone())?.to_string(), expected.to_owned());
+
+	let mut out = Vec::new();
+	v.manifest_to(s.clone(), &s.manifest_format(), &mut out)?;
+	ensure_eq!(String::from_utf8(out).expect("utf8"), expected.to_owned());
+
+	Ok(())
+}
+
+#[test]
+fn to_string_spacing() -> Result<()> {
+	use jrsonnet_evaluator::manifest::{manifest_json_ex, ManifestJsonOptions, ManifestType};
+
+	let s = State::default();
+	s.with_stdlib();
+
+	let v = s.evaluate_snippet("snip".to_owned(), "{a: [1, 2, {}], b: []}".into())?;
+	ensure_eq!(
+		v.to_string(s.clone())?.to_string(),
+		r#"{"a": [1, 2, { }], "b": [ ]}"#.to_owned()
+	);
+	let compact = manifest_json_ex(
+		s,
+		&v,
+		&ManifestJsonOptions {
+			padding: "",
+			mtype: ManifestType::ToString,
+			key_val_sep: ":",
+			item_sep: "",
+			empty_padding: "",
+			..ManifestJsonOptions::default()
+		},
+	)?;
+	ensure_eq!(compact, r#"{"a":[1,2,{}],"b":[]}"#.to_owned());
+
+	Ok(())
+}
+
+#[test]
+fn json_styler() -> Result<()> {
+	use jrsonnet_evaluator::manifest::{
+		manifest_json_ex, JsonStyler, JsonToken, ManifestJsonOptions, ManifestType,
+	};
+
+	struct Brackets;
+	impl JsonStyler for Brackets {
+		fn start(&self, token: JsonToken, buf: &mut String) {
+			if token != JsonToken::Punctuation {
+				buf.push('<');
+			}
+		}
+		fn end(&self, token: JsonToken, buf: &mut String) {
+			if token != JsonToken::Punctuation {
+				buf.push('>');
+			}
+		}
+	}
+
+	let s = State::default();
+	s.with_stdlib();
+
+	let v = s.evaluate_snippet("snip".to_owned(), "{a: [1, 'x', true, null]}".into())?;
+	let styled = manifest_json_ex(
+		s.clone(),
+		&v,
+		&ManifestJsonOptions {
+			padding: "",
+			mtype: ManifestType::ToString,
+			key_val_sep: ":",
+			item_sep: "",
+			empty_padding: "",
+			styler: &Brackets,
+			..ManifestJsonOptions::default()
+		},
+	)?;
+	ensure_eq!(styled, r#"{<"a">:[<1>,<"x">,<true>,<null>]}"#.to_owned());
+
+	s.set_manifest_format(ManifestFormat::Json {
+		padding: "  ".to_owned(),
+		#[cfg(feature = "exp-preserve-order")]
+		preserve_order: false,
+	});
+	ensure!(!s.manifest(v.clone())?.contains('\x1b'));
+	s.set_highlight_output(true);
+	let highlighted = s.manifest(v)?;
+	ensure!(highlighted.contains("\x1b[34m\"a\"\x1b[0m: ["));
+	ensure!(highlighted.contains("\x1b[33m1\x1b[0m,"));
+
+	Ok(())
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "exp-preserve-order")]
+
+mod common;
+
+use jrsonnet_evaluator::{error::Result, throw_runtime, State, Val};
+
+#[test]
+fn equals_ordered() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+
+	let eval = |code: &str| s.evaluate_snippet("snip".to_owned(), code.to_owned());
+	let a = eval("{a: 1, b: [{c: 1, d: 2}]}")?;
+	let b = eval("{b: [{c: 1, d: 2}], a: 1}")?;
+	let c = eval("{a: 1, b: [{d: 2, c: 1}]}")?;
+
+	ensure!(a.equals_ordered(s.clone(), &eval("{a: 1} + {b: [{c: 1, d: 2}]}")?)?);
+	ensure!(!a.equals_ordered(s.clone(), &b)?);
+	ensure!(!a.equals_ordered(s.clone(), &c)?);
+	ensure!(!a.equals_ordered(s.clone(), &eval("{a: 1, b: [{c: 1, d: 3}]}")?)?);
+	// `==` still ignores ordering
+	ensure!(jrsonnet_evaluator::val::equals(s.clone(), &a, &b)?);
+
+	Ok(())
+}
+
+#[test]
+fn preserve_order_merges() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+
+	let fields = |code: &str| -> Result<Vec<String>> {
+		let v = s.evaluate_snippet("snip".to_owned(), code.to_owned())?;
+		let obj = match v {
+			Val::Obj(obj) => obj,
+			_ => throw_runtime!("expected object"),
+		};
+		Ok(obj.fields(true).iter().map(ToString::to_string).collect())
+	};
+
+	ensure_eq!(fields("{b: 1, a: 2} + {c: 3, b: 4}")?, ["b", "a", "c"]);
+	ensure_eq!(
+		fields("({b: 1} + {a: 2}) + ({d: 3, b: 4} + {c: 5, a: 6})")?,
+		["b", "a", "d", "c"]
+	);
+	ensure_eq!(
+		fields("{z: 1} + ({y: 2} + {z: 3, x: 4})")?,
+		fields("({z: 1} + {y: 2}) + {z: 3, x: 4}")?
+	);
+	ensure_eq!(
+		fields("{b: 1, a: 2} + {b:: 3, c: 4} + {b::: 5, d: 6}")?,
+		["b", "a", "c", "d"]
+	);
+	ensure_eq!(fields("{b: 1, a: 2} + {b:: 3, c: 4}")?, ["a", "c"]);
+	ensure_eq!(
+		fields("{b: 1} + {['x' + i]: i for i in ['2', '1']} + {a: 3, x1: 4}")?,
+		["b", "x2", "x1", "a"]
+	);
+
+	Ok(())
+}
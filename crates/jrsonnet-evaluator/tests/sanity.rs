@@ -1,4 +1,4 @@
-use jrsonnet_evaluator::{error::Result, throw_runtime, State, Val};
+use jrsonnet_evaluator::{error::Result, throw_runtime, Context, IStr, State, Val};
 
 mod common;
 
@@ -39,3 +39,501 @@ fn assert_negative() -> Result<()> {
 
 	Ok(())
 }
+
+#[test]
+fn error_structured_payload() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+
+	{
+		let e = match s.evaluate_snippet("snip".to_owned(), "error {code: 404}".into()) {
+			Ok(_) => throw_runtime!("error should be thrown"),
+			Err(e) => e,
+		};
+		let payload = match e.error().payload() {
+			Some(p) => p.clone(),
+			None => throw_runtime!("error should have payload"),
+		};
+		let expected = s.evaluate_snippet("snip".to_owned(), "{code: 404}".into())?;
+		ensure_val_eq!(s, payload, expected);
+		let e = s.stringify_err(&e);
+		ensure!(e.starts_with("runtime error: {\"code\": 404}\n"));
+	}
+	{
+		let e = match s.evaluate_snippet("snip".to_owned(), "error 'fail'".into()) {
+			Ok(_) => throw_runtime!("error should be thrown"),
+			Err(e) => e,
+		};
+		ensure!(e.error().payload().is_none());
+	}
+
+	Ok(())
+}
+
+#[test]
+fn trace_capture() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+
+	s.start_trace_capture();
+	s.evaluate_snippet(
+		"snip".to_owned(),
+		"std.trace('first', std.trace('second', null))".into(),
+	)?;
+	let captured = s.finish_trace_capture();
+	ensure_eq!(
+		captured,
+		Some("TRACE: snip:1 second\nTRACE: snip:1 first\n".to_owned())
+	);
+	ensure_eq!(s.finish_trace_capture(), None);
+
+	Ok(())
+}
+
+#[test]
+fn make_array_element_evaluated_once() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+
+	s.start_trace_capture();
+	let v = s.evaluate_snippet(
+		"snip".to_owned(),
+		"local a = std.makeArray(3, function(i) std.trace('elem ' + i, i)); a[1] + a[1] + std.foldl(function(acc, v) acc + v, a, 0)"
+			.into(),
+	)?;
+	ensure_val_eq!(s, v, Val::Num(5.0));
+	let captured = s.finish_trace_capture().unwrap_or_default();
+	ensure_eq!(captured.matches(" elem 1\n").count(), 1);
+	ensure_eq!(captured.matches(" elem 0\n").count(), 1);
+
+	Ok(())
+}
+
+#[test]
+fn assert_operands_evaluated_once() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+
+	s.start_trace_capture();
+	let e = match s.evaluate_snippet(
+		"snip".to_owned(),
+		"assert std.trace('lhs', 1) == std.trace('rhs', 2); null".into(),
+	) {
+		Ok(_) => throw_runtime!("assertion should fail"),
+		Err(e) => e.error().to_string(),
+	};
+	ensure!(e.ends_with(": 1 != 2"));
+	let captured = s.finish_trace_capture().unwrap_or_default();
+	ensure_eq!(captured.matches(" lhs\n").count(), 1);
+	ensure_eq!(captured.matches(" rhs\n").count(), 1);
+
+	Ok(())
+}
+
+#[test]
+fn materialize() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+
+	let v = s.evaluate_snippet(
+		"snip".to_owned(),
+		"{a: [1, {b: std.range(1, 3)}], c:: self.a, d:: error 'hidden'}".into(),
+	)?;
+	let m = v.materialize(s.clone())?;
+	ensure_val_eq!(s, m, v);
+	let obj = m.as_obj().expect("object");
+	ensure!(!obj.has_field_ex("c".into(), true));
+	ensure!(!obj.has_field_ex("d".into(), true));
+
+	#[cfg(feature = "exp-preserve-order")]
+	{
+		let v = s.evaluate_snippet("snip".to_owned(), "{b: 1, a: {d: 2, c: 3}}".into())?;
+		let m = v.materialize(s.clone())?;
+		ensure!(m.equals_ordered(s.clone(), &v)?);
+		ensure_eq!(
+			m.as_obj().expect("object").fields(true),
+			vec![IStr::from("b"), IStr::from("a")]
+		);
+	}
+
+	let v = s.evaluate_snippet("snip".to_owned(), "local a = {b: [a]}; a".into())?;
+	let e = match v.materialize(s.clone()) {
+		Ok(_) => throw_runtime!("cyclic value should not be materialized"),
+		Err(e) => e,
+	};
+	ensure_eq!(
+		e.error().to_string(),
+		"tried to materialize cyclic value".to_owned()
+	);
+
+	Ok(())
+}
+
+#[test]
+fn hash_consistent_with_equals() -> Result<()> {
+	use std::{collections::hash_map::DefaultHasher, hash::Hasher};
+
+	use jrsonnet_evaluator::val::{equals, hash_val};
+
+	let s = State::default();
+	s.with_stdlib();
+
+	let hash = |v: &Val| -> Result<u64> {
+		let mut hasher = DefaultHasher::new();
+		hash_val(s.clone(), v, &mut hasher)?;
+		Ok(hasher.finish())
+	};
+	let v = s.evaluate_snippet(
+		"snip".to_owned(),
+		"[{a: 1, b: [1.0, 'x']}, {b: [1, 'x']} + {a: 1, c:: 2}, -0.0, 0, 0.1 + 0.2, 0.3]".into(),
+	)?;
+	let mut items: Vec<Val> = v
+		.as_arr()
+		.expect("array")
+		.iter(s.clone())
+		.collect::<Result<_>>()?;
+	// Largest float below 2 is within epsilon from it
+	let below_two = f64::from_bits(2.0f64.to_bits() - 1);
+	items.extend([Val::Num(2.0), Val::Num(below_two)]);
+	items.extend([Val::Num(-2.0), Val::Num(-below_two)]);
+	for (a, b) in [(0, 1), (2, 3), (4, 5), (6, 7), (8, 9)] {
+		ensure!(equals(s.clone(), &items[a], &items[b])?);
+		ensure_eq!(hash(&items[a])?, hash(&items[b])?);
+	}
+
+	Ok(())
+}
+
+#[test]
+fn hide_std_frames() -> Result<()> {
+	use jrsonnet_evaluator::trace::{CompactFormat, FrameFilter, PathResolver};
+
+	let s = State::default();
+	s.with_stdlib();
+	let format = |filter| {
+		Box::new(CompactFormat {
+			resolver: PathResolver::Absolute,
+			padding: 4,
+			separator: " ".to_owned(),
+			filter,
+		})
+	};
+
+	let e = match s.evaluate_snippet(
+		"snip".to_owned(),
+		"local f(o) = std.objectValues(o)[0]; f({a: error 'fail'})".into(),
+	) {
+		Ok(_) => throw_runtime!("error should be thrown"),
+		Err(e) => e,
+	};
+
+	s.set_trace_format(format(FrameFilter::None));
+	let full = s.stringify_err(&e);
+	ensure!(full.contains("<std>:"));
+	ensure!(!full.contains("std frame"));
+
+	s.set_trace_format(format(FrameFilter::Std));
+	let filtered = s.stringify_err(&e);
+	ensure!(!filtered.contains("<std>:"));
+	ensure!(filtered.contains("std frame"));
+	ensure!(filtered.contains("snip:"));
+
+	Ok(())
+}
+
+#[test]
+fn union_check_fast() -> Result<()> {
+	use jrsonnet_evaluator::typed::{CheckType, ComplexValType, ValType};
+
+	let s = State::default();
+	let simple = ComplexValType::Union(vec![
+		ValType::Str.into(),
+		ValType::Num.into(),
+		ValType::Null.into(),
+	]);
+	ensure_eq!(simple.check_fast(&Val::Num(1.0)), Some(true));
+	ensure_eq!(simple.check_fast(&Val::Bool(true)), Some(false));
+	ensure!(simple.check(s.clone(), &Val::Null).is_ok());
+	ensure!(simple.check(s.clone(), &Val::Bool(true)).is_err());
+
+	// Array elements can't be checked cheaply, but type of the value itself can
+	let nested = ComplexValType::Union(vec![
+		ComplexValType::Array(Box::new(ValType::Num.into())),
+		ValType::Str.into(),
+	]);
+	ensure_eq!(nested.check_fast(&Val::Str("a".into())), Some(true));
+	ensure_eq!(nested.check_fast(&Val::Num(1.0)), Some(false));
+	let arr = Val::Arr(vec![Val::Num(1.0)].into());
+	ensure_eq!(nested.check_fast(&arr), None);
+	ensure!(nested.check(s, &arr).is_ok());
+
+	Ok(())
+}
+
+#[test]
+fn object_layers() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+
+	let v = s.evaluate_snippet(
+		"snip".to_owned(),
+		"{a: 1, b: 2} + {b: super.b + 1, c:: 3} + {d: 4}".into(),
+	)?;
+	let obj = match v {
+		Val::Obj(obj) => obj,
+		_ => throw_runtime!("expected object"),
+	};
+	let names = |fields: Vec<IStr>| fields.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+	ensure_eq!(names(obj.own_fields()), ["d"]);
+	let layers = obj.field_layers();
+	ensure_eq!(layers.len(), 4);
+	ensure_eq!(layers[&IStr::from("a")], [2]);
+	ensure_eq!(layers[&IStr::from("b")], [1, 2]);
+	ensure_eq!(layers[&IStr::from("c")], [1]);
+	ensure_eq!(layers[&IStr::from("d")], [0]);
+
+	let sup = obj.super_object().expect("object is extended");
+	ensure_eq!(names(sup.own_fields()), ["b", "c"]);
+	let base = sup.super_object().expect("object is extended");
+	ensure_eq!(names(base.own_fields()), ["a", "b"]);
+	ensure!(base.super_object().is_none());
+
+	Ok(())
+}
+
+#[test]
+fn filter_with_progress() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+
+	let arr = match s.evaluate_snippet("snip".to_owned(), "std.range(1, 5)".into())? {
+		Val::Arr(arr) => arr,
+		_ => throw_runtime!("expected array"),
+	};
+	let mut reports = Vec::new();
+	let filtered = arr.filter_with_progress(
+		s,
+		|v| Ok(matches!(v, Val::Num(n) if *n > 2.0)),
+		2,
+		|kept, scanned| {
+			reports.push((kept, scanned));
+			Ok(())
+		},
+	)?;
+	ensure_eq!(filtered.len(), 3);
+	ensure_eq!(reports, [(0, 2), (2, 4), (3, 5)]);
+
+	Ok(())
+}
+
+#[test]
+fn evaluate_timed() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+
+	ensure_eq!(s.thunks_forced(), None);
+	s.evaluate_snippet("snip".to_owned(), "local a = 1 + 1; a + a".into())?;
+	ensure_eq!(s.thunks_forced(), None);
+
+	s.set_count_thunks_forced(true);
+	ensure_eq!(s.thunks_forced(), Some(0));
+	let (v, _) = s.evaluate_snippet_timed("snip".to_owned(), "local a = 1 + 1; a + a".into())?;
+	ensure_val_eq!(s, v, Val::Num(4.0));
+	let forced = s.thunks_forced().expect("counting is enabled");
+	ensure!(forced > 0);
+
+	let parsed = s.parse_snippet("snip".to_owned(), "[1, 2]".into())?;
+	let (v, _) = s.evaluate_expr_timed(&parsed)?;
+	ensure!(matches!(v, Val::Arr(a) if a.len() == 2));
+	// Array elements are not evaluated
+	ensure_eq!(s.thunks_forced(), Some(forced));
+
+	s.set_count_thunks_forced(false);
+	ensure_eq!(s.thunks_forced(), None);
+
+	Ok(())
+}
+
+#[test]
+fn assert_type_invalid_spec() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+
+	let e = match s.evaluate_snippet("snip".to_owned(), "std.assertType(1, 'numbr')".into()) {
+		Ok(_) => throw_runtime!("invalid spec should fail"),
+		Err(e) => e,
+	};
+	ensure!(e
+		.error()
+		.to_string()
+		.starts_with("runtime error: invalid type spec \"numbr\": "));
+
+	Ok(())
+}
+
+#[test]
+fn super_field_evaluated_once() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+
+	s.start_trace_capture();
+	let v = s.evaluate_snippet(
+		"snip".to_owned(),
+		r#"
+			local base = { x: std.trace('field', 1), assert std.trace('assertion', true) };
+			local obj = base + { y: super.x + super.x + super.x };
+			obj.y + obj.x
+		"#
+		.into(),
+	)?;
+	ensure_val_eq!(s, v, Val::Num(4.0));
+	let captured = s.finish_trace_capture().unwrap_or_default();
+	ensure_eq!(captured.matches(" field\n").count(), 1);
+	ensure_eq!(captured.matches(" assertion\n").count(), 1);
+
+	// Every object instance still has its own value of inherited field
+	let v = s.evaluate_snippet(
+		"snip".to_owned(),
+		r#"
+			local base = { x: self.k };
+			local a = base + { k: 1, y: super.x };
+			local b = base + { k: 2, y: super.x };
+			[a.y, b.y, (a + { k: 3 }).y, (base + { k: 4 }).x]
+		"#
+		.into(),
+	)?;
+	ensure_eq!(
+		v.to_json(
+			s,
+			"",
+			#[cfg(feature = "exp-preserve-order")]
+			false,
+		)?
+		.to_string(),
+		"[1,2,3,4]".to_owned()
+	);
+
+	Ok(())
+}
+
+#[test]
+fn collect_cycles_on_drop() -> Result<()> {
+	let tracked = jrsonnet_gcmodule::count_thread_tracked();
+	{
+		let s = State::default();
+		s.with_stdlib();
+		s.settings_mut().collect_cycles_on_drop = true;
+
+		let v = s.evaluate_snippet(
+			"snip".to_owned(),
+			"local o = { a: o.b, b: 'x' }; o.a".into(),
+		)?;
+		ensure_val_eq!(s, v, Val::Str("x".into()));
+	}
+	ensure_eq!(jrsonnet_gcmodule::count_thread_tracked(), tracked);
+
+	Ok(())
+}
+
+#[test]
+fn collect_cycles_on_drop_waits_for_other_states() -> Result<()> {
+	let tracked = jrsonnet_gcmodule::count_thread_tracked();
+	let other = State::default();
+	{
+		let s = State::default();
+		s.settings_mut().collect_cycles_on_drop = true;
+
+		let v = s.evaluate_snippet(
+			"snip".to_owned(),
+			"local o = { a: o.b, b: 'x' }; o.a".into(),
+		)?;
+		ensure_val_eq!(s, v, Val::Str("x".into()));
+	}
+	// Collection would also scan values of the other state, so it is skipped
+	ensure!(jrsonnet_gcmodule::count_thread_tracked() > tracked);
+
+	other.settings_mut().collect_cycles_on_drop = true;
+	drop(other);
+	ensure_eq!(jrsonnet_gcmodule::count_thread_tracked(), tracked);
+
+	Ok(())
+}
+
+#[test]
+fn visible_bindings() -> Result<()> {
+	let ctx = Context::new()
+		.with_var("b".into(), Val::Null)
+		.with_var("a".into(), Val::Null)
+		.with_var("b".into(), Val::Bool(true));
+	ensure_eq!(
+		ctx.visible_bindings(),
+		vec![IStr::from("a"), IStr::from("b")]
+	);
+	ensure_eq!(Context::new().visible_bindings(), Vec::<IStr>::new());
+	Ok(())
+}
+
+#[test]
+fn negative_index() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+
+	let err = |code: &str| -> Result<String> {
+		match s.evaluate_snippet("snip".to_owned(), code.to_owned()) {
+			Ok(_) => throw_runtime!("{} should fail", code),
+			Err(e) => Ok(e.error().to_string()),
+		}
+	};
+
+	#[cfg(feature = "ext-negative-index")]
+	{
+		let v = s.evaluate_snippet("snip".to_owned(), "[[1, 2, 3][-1], [1, 2, 3][-3]]".into())?;
+		ensure_eq!(v.to_string(s.clone())?.to_string(), "[3, 1]".to_owned());
+		ensure_eq!(
+			err("[1, 2, 3][-4]")?,
+			"array out of bounds: -4 is not within [0,3)".to_owned()
+		);
+	}
+	#[cfg(not(feature = "ext-negative-index"))]
+	ensure_eq!(
+		err("[1, 2, 3][-1]")?,
+		"array out of bounds: -1 is not within [0,3)".to_owned()
+	);
+	ensure_eq!(
+		err("[1, 2, 3][-0.5]")?,
+		"tried to index by fractional value".to_owned()
+	);
+	ensure_eq!(
+		err("'abc'[-1]")?,
+		"string out of bounds: -1 is not within [0,3)".to_owned()
+	);
+
+	Ok(())
+}
+
+#[test]
+fn sort_nan() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+	s.settings_mut()
+		.globals
+		.insert("nan".into(), Val::Num(f64::NAN));
+
+	for code in [
+		"std.sort([1, nan, 2])",
+		"std.sort([1, 2, 3], function(x) if x == 2 then nan else x)",
+		"std.sort([1, nan], reverse=true)",
+		"std.set([1, nan])",
+	] {
+		match s.evaluate_snippet("snip".to_owned(), code.to_owned()) {
+			Ok(_) => throw_runtime!("{} should fail", code),
+			Err(e) => ensure_eq!(
+				e.error().to_string(),
+				"runtime error: cannot sort NaN".to_owned()
+			),
+		}
+	}
+
+	Ok(())
+}
@@ -0,0 +1,89 @@
+mod common;
+
+use jrsonnet_evaluator::{error::Result, throw_runtime, State, Val};
+
+#[test]
+fn stdlib_name() -> Result<()> {
+	let s = State::default();
+	s.set_stdlib_name("lib".into());
+	s.with_stdlib();
+
+	let v = s.evaluate_snippet(
+		"snip".to_owned(),
+		"local std = {custom: true}; std.custom && lib.length(lib.set([2, 1, 2])) == 2".into(),
+	)?;
+	ensure_val_eq!(s, v, Val::Bool(true));
+
+	s.set_stdlib_name("std".into());
+	let v = s.evaluate_snippet("snip".to_owned(), "std.length([1])".into())?;
+	ensure_val_eq!(s, v, Val::Num(1.0));
+
+	Ok(())
+}
+
+#[test]
+fn stdlib_subset() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib_subset(&["objectHas".into(), "join".into()]);
+
+	// objectHas is implemented via objectHasEx, which is kept as dependency
+	let v = s.evaluate_snippet(
+		"snip".to_owned(),
+		"std.objectHas({a: 1}, 'a') && std.join(',', ['a', 'b']) == 'a,b'".into(),
+	)?;
+	ensure_val_eq!(s, v, Val::Bool(true));
+
+	let e = match s.evaluate_snippet("snip".to_owned(), "std.manifestYamlStream([])".into()) {
+		Ok(_) => throw_runtime!("omitted function should fail"),
+		Err(e) => e,
+	};
+	ensure_eq!(
+		e.error().to_string(),
+		"function manifestYamlStream not available in minimal stdlib".to_owned()
+	);
+
+	Ok(())
+}
+
+#[test]
+fn stdlib_path() -> Result<()> {
+	use jrsonnet_evaluator::error::Error;
+
+	let dir = std::env::temp_dir().join(format!("jrsonnet-stdlib-path-{}", std::process::id()));
+	std::fs::create_dir_all(&dir).unwrap();
+	let write = |name: &str, code: &str| {
+		let path = dir.join(name);
+		std::fs::write(&path, code).unwrap();
+		path
+	};
+
+	let s = State::default();
+	s.with_stdlib();
+	s.set_stdlib_path(&write(
+		"std.jsonnet",
+		"{ answer: 42, join:: $intrinsic(join) }",
+	))?;
+	let v = s.evaluate_snippet(
+		"snip".to_owned(),
+		"std.answer == 42 && std.join(',', ['a', 'b']) == 'a,b' && !('length' in std)".into(),
+	)?;
+	ensure_val_eq!(s, v, Val::Bool(true));
+
+	let e = match s.set_stdlib_path(&write("array.jsonnet", "[]")) {
+		Ok(_) => throw_runtime!("non-object stdlib should fail"),
+		Err(e) => e,
+	};
+	ensure!(e.error().to_string().ends_with("should be an object"));
+	let e = match s.set_stdlib_path(&write("broken.jsonnet", "{ a: }")) {
+		Ok(_) => throw_runtime!("syntax error should fail"),
+		Err(e) => e,
+	};
+	ensure!(matches!(e.error(), Error::ImportSyntaxError { .. }));
+
+	// Failed loads keep previous stdlib
+	let v = s.evaluate_snippet("snip".to_owned(), "std.answer".into())?;
+	ensure_val_eq!(s, v, Val::Num(42.0));
+
+	std::fs::remove_dir_all(&dir).unwrap();
+	Ok(())
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use jrsonnet_evaluator::{error::Result, throw_runtime, typed::Typed, State, Val};
+use serde_json::Value;
+
+/// Asserts that `std.manifestTomlEx(value, '  ', ...)` is parsed back to the same value by real TOML parser
+fn assert_toml_roundtrip(s: &State, value: &str, args: &str) -> Result<()> {
+	let v = s.evaluate_snippet("snip".to_owned(), value.to_owned())?;
+	let manifested = match s.evaluate_snippet(
+		"snip".to_owned(),
+		format!("std.manifestTomlEx({}, '  '{})", value, args),
+	)? {
+		Val::Str(v) => v,
+		_ => throw_runtime!("manifestTomlEx should return string"),
+	};
+	let parsed: Value = match toml::from_str(&manifested) {
+		Ok(v) => v,
+		Err(e) => throw_runtime!("failed to parse toml: {}\n{}", e, manifested),
+	};
+	let parsed = Value::into_untyped(parsed, s.clone())?;
+	ensure_val_eq!(s, v, parsed);
+	Ok(())
+}
+
+const CARGO: &str = r#"{
+	package: {
+		name: 'jrsonnet',
+		version: '0.4.2',
+		authors: ['Yaroslav Bolyukin <iam@lach.pw>'],
+		metadata: { docs: { rs: { 'all-features': true } } },
+	},
+	dependencies: {
+		serde: { version: '1.0', features: ['derive'] },
+		'serde_json': '1.0',
+		'local': { path: '../local' },
+	},
+	bin: [
+		{ name: 'a', path: 'src/a.rs', 'required-features': ['x'] },
+		{ name: 'b', test: [{ name: 't1' }, { name: 't2', harness: false }] },
+	],
+	'target.cfg(unix)': { dependencies: {} },
+	weird: { 'with.dot': 1, 'with space': 2, '': 3, 'quote"': 4, 'tab\t': 5 },
+	values: {
+		string: 'esc"aped\n\u0001\u007f',
+		float: 1.5,
+		int: -2,
+		big: 1e21,
+		nested: [[1], []],
+		inline: [[{ a: 1 }], []],
+	},
+}"#;
+
+#[test]
+fn toml_roundtrip() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+
+	assert_toml_roundtrip(&s, CARGO, "")?;
+	assert_toml_roundtrip(&s, CARGO, ", inline_table_max_fields=2")?;
+	assert_toml_roundtrip(
+		&s,
+		CARGO,
+		", inline_table_max_fields=3, inline_table_max_depth=2",
+	)?;
+
+	Ok(())
+}
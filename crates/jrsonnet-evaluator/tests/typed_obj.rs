@@ -2,7 +2,7 @@ mod common;
 
 use std::fmt::Debug;
 
-use jrsonnet_evaluator::{error::Result, typed::Typed, State};
+use jrsonnet_evaluator::{error::Result, throw_runtime, typed::Typed, State};
 
 #[derive(Clone, Typed, PartialEq, Debug)]
 struct A {
@@ -192,3 +192,40 @@ fn flatten_optional_none() -> Result<()> {
 	test_roundtrip(d, s)?;
 	Ok(())
 }
+
+#[derive(Clone, Typed, PartialEq, Debug)]
+struct Inner {
+	name: String,
+}
+
+#[derive(Clone, Typed, PartialEq, Debug)]
+struct Outer {
+	inner: Inner,
+}
+
+fn parse_error<T: Typed + Debug>(code: &str) -> Result<String> {
+	let s = State::default();
+	s.with_stdlib();
+	let value = s.evaluate_snippet("snip".to_owned(), code.into())?;
+	match T::from_untyped(value, s) {
+		Ok(v) => throw_runtime!("expected error, got {:?}", v),
+		Err(e) => Ok(e.error().to_string()),
+	}
+}
+
+#[test]
+fn invalid_object() -> Result<()> {
+	ensure_eq!(
+		parse_error::<Outer>("{inner: {}}")?,
+		r#"type error: missing property name from {name: string} at self."inner""#.to_owned(),
+	);
+	ensure_eq!(
+		parse_error::<Outer>("{inner: {name: 1}}")?,
+		r#"type error: expected string, got number at self."inner"."name""#.to_owned(),
+	);
+	ensure_eq!(
+		parse_error::<Outer>("1")?,
+		"type error: expected {inner: {name: string}}, got number".to_owned(),
+	);
+	Ok(())
+}
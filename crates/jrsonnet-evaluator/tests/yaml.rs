@@ -0,0 +1,115 @@
+#![cfg(feature = "yaml")]
+
+mod common;
+
+use jrsonnet_evaluator::{error::Result, throw_runtime, State, Val};
+
+#[test]
+fn yaml_top_level_scalar() -> Result<()> {
+	let s = State::default();
+
+	let yaml = |v: Val| {
+		v.to_yaml(
+			s.clone(),
+			2,
+			#[cfg(feature = "exp-preserve-order")]
+			false,
+		)
+	};
+	ensure_eq!(
+		yaml(Val::Str("hello".into()))?.to_string(),
+		"hello".to_owned()
+	);
+	ensure_eq!(yaml(Val::Str("".into()))?.to_string(), "\"\"".to_owned());
+	ensure_eq!(yaml(Val::Str("1".into()))?.to_string(), "\"1\"".to_owned());
+	ensure_eq!(yaml(Val::Num(1.0))?.to_string(), "1".to_owned());
+
+	Ok(())
+}
+
+#[test]
+fn json_yaml_key_order() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+
+	let v = s.evaluate_snippet(
+		"snip".to_owned(),
+		"{b: 1, a: {d: 1, c: 2}} + {e: 3, a+: {f: 4}}".into(),
+	)?;
+	let keys = ["a", "b", "c", "d", "e", "f"];
+	let key_order = |out: &str, quote: &str| {
+		let mut order = keys.to_vec();
+		order.sort_by_key(|k| {
+			out.find(&format!("{quote}{k}{quote}:"))
+				.expect("key exists")
+		});
+		order
+	};
+
+	#[cfg(feature = "exp-preserve-order")]
+	let modes = [false, true];
+	#[cfg(not(feature = "exp-preserve-order"))]
+	let modes = [false];
+	for preserve_order in modes {
+		let json = v.to_json(
+			s.clone(),
+			"  ",
+			#[cfg(feature = "exp-preserve-order")]
+			preserve_order,
+		)?;
+		let yaml = v.to_yaml(
+			s.clone(),
+			2,
+			#[cfg(feature = "exp-preserve-order")]
+			preserve_order,
+		)?;
+		ensure_eq!(key_order(&json, "\""), key_order(&yaml, ""));
+		if !preserve_order {
+			// Every object is sorted separately, nested keys are written inside of their parent
+			ensure_eq!(key_order(&json, "\""), ["a", "c", "d", "f", "b", "e"]);
+		}
+	}
+
+	Ok(())
+}
+
+#[test]
+fn yaml_field_error_trace() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+
+	let v = s.evaluate_snippet("snip".to_owned(), "{a: {b: error 'fail'}}".into())?;
+	let e = match v.to_yaml(
+		s.clone(),
+		2,
+		#[cfg(feature = "exp-preserve-order")]
+		false,
+	) {
+		Ok(_) => throw_runtime!("manifestification should fail"),
+		Err(e) => e,
+	};
+	let e = s.stringify_err(&e);
+	ensure!(e.starts_with("runtime error: fail\n"));
+	ensure!(e.contains("field <a> manifestification"));
+	ensure!(e.contains("field <b> manifestification"));
+
+	Ok(())
+}
+
+#[test]
+fn parse_yaml_error_location() -> Result<()> {
+	let s = State::default();
+	s.with_stdlib();
+
+	let e = match s.evaluate_snippet(
+		"snip".to_owned(),
+		"std.parseYaml('a: 1\\nb: *missing')".into(),
+	) {
+		Ok(_) => throw_runtime!("unknown alias should fail"),
+		Err(e) => e.error().to_string(),
+	};
+	ensure!(e.starts_with("runtime error: failed to parse yaml: "));
+	ensure!(e.contains("unknown anchor at line 2 column"));
+
+	Ok(())
+}
@@ -0,0 +1,36 @@
+use std::thread;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use jrsonnet_interner::{intern_str, set_pool_capacity, DEFAULT_POOL_CAPACITY};
+
+/// Interning of many unique strings into a fresh pool, with default and preset pool capacity
+///
+/// Every iteration runs on a new thread, as pool is thread-local without `sync-interner` feature,
+/// and its capacity can only be set before the first interning
+fn pool_capacity(c: &mut Criterion) {
+	let mut group = c.benchmark_group("pool_capacity");
+	for count in [10_000, 100_000] {
+		let strings = (0..count)
+			.map(|i| format!("field_{}", i))
+			.collect::<Vec<_>>();
+		for (name, capacity) in [("default", DEFAULT_POOL_CAPACITY), ("preset", count)] {
+			group.bench_with_input(BenchmarkId::new(name, count), &strings, |b, strings| {
+				b.iter(|| {
+					thread::scope(|scope| {
+						scope.spawn(|| {
+							set_pool_capacity(capacity);
+							// Interned strings can't leave the thread without `sync-interner`
+							let interned =
+								strings.iter().map(|s| intern_str(s)).collect::<Vec<_>>();
+							interned.len()
+						});
+					});
+				});
+			});
+		}
+	}
+	group.finish();
+}
+
+criterion_group!(benches, pool_capacity);
+criterion_main!(benches);
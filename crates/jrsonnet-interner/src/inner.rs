@@ -6,46 +6,130 @@ use std::{
 	mem,
 	ptr::{self, NonNull},
 	slice, str,
+	sync::atomic::{AtomicU32, Ordering},
 };
 
+#[cfg(feature = "sync-interner")]
+use std::sync::atomic::fence;
+
 const UTF8_MASK: u32 = 1 << 31;
-const REFCNT_MASK: u32 = !UTF8_MASK;
+const ASCII_MASK: u32 = 1 << 30;
+const REFCNT_MASK: u32 = !(UTF8_MASK | ASCII_MASK);
 
 #[repr(C)]
 struct InnerHeader {
 	size: u32,
-	// MSB is checked utf8 flag, rest - refcnt
-	utf8_refcnt: u32,
+	// MSB is checked utf8 flag, next bit is ascii flag, rest - refcnt
+	// Read-modify-write operations are only atomic with `sync-interner` feature,
+	// otherwise plain loads and stores are used
+	utf8_refcnt: AtomicU32,
 }
 impl InnerHeader {
-	const fn new(size: u32, is_utf8: bool) -> Self {
+	const fn new(size: u32, is_utf8: bool, is_ascii: bool) -> Self {
 		Self {
 			size,
-			utf8_refcnt: 1 | (if is_utf8 { UTF8_MASK } else { 0 }),
+			utf8_refcnt: AtomicU32::new(
+				1 | (if is_utf8 || is_ascii { UTF8_MASK } else { 0 })
+					| (if is_ascii { ASCII_MASK } else { 0 }),
+			),
 		}
 	}
 
-	const fn refcnt(&self) -> u32 {
-		self.utf8_refcnt & REFCNT_MASK
+	fn load(&self) -> u32 {
+		self.utf8_refcnt.load(Ordering::Relaxed)
+	}
+	fn refcnt(&self) -> u32 {
+		self.load() & REFCNT_MASK
+	}
+	fn is_utf8(&self) -> bool {
+		self.load() & UTF8_MASK != 0
 	}
-	const fn is_utf8(&self) -> bool {
-		self.utf8_refcnt & UTF8_MASK != 0
+	fn is_ascii(&self) -> bool {
+		self.load() & ASCII_MASK != 0
 	}
 
-	fn set_refcnt(&mut self, cnt: u32) {
-		assert_eq!(cnt & UTF8_MASK, 0);
-		// Reset all bits expect last
-		self.utf8_refcnt &= UTF8_MASK;
-		// Store refcnt
-		self.utf8_refcnt |= cnt;
+	fn set_is_utf8(&self) {
+		#[cfg(feature = "sync-interner")]
+		self.utf8_refcnt.fetch_or(UTF8_MASK, Ordering::Relaxed);
+		#[cfg(not(feature = "sync-interner"))]
+		self.utf8_refcnt
+			.store(self.load() | UTF8_MASK, Ordering::Relaxed);
 	}
-	fn set_is_utf8(&mut self) {
-		self.utf8_refcnt |= UTF8_MASK;
+	/// # Panics
+	/// If refcnt is already at its maximum, before anything is written,
+	/// as incrementing it would carry into the flag bits
+	fn inc_refcnt(&self) {
+		#[cfg(feature = "sync-interner")]
+		{
+			let mut cur = self.load();
+			loop {
+				assert_ne!(cur & REFCNT_MASK, REFCNT_MASK, "refcnt overflow");
+				match self.utf8_refcnt.compare_exchange_weak(
+					cur,
+					cur + 1,
+					Ordering::Relaxed,
+					Ordering::Relaxed,
+				) {
+					Ok(_) => return,
+					Err(actual) => cur = actual,
+				}
+			}
+		}
+		#[cfg(not(feature = "sync-interner"))]
+		{
+			let cur = self.load();
+			assert_ne!(cur & REFCNT_MASK, REFCNT_MASK, "refcnt overflow");
+			self.utf8_refcnt.store(cur + 1, Ordering::Relaxed);
+		}
+	}
+	/// Returns new refcnt
+	fn dec_refcnt(&self) -> u32 {
+		#[cfg(feature = "sync-interner")]
+		let prev = {
+			let prev = self.utf8_refcnt.fetch_sub(1, Ordering::Release);
+			if prev & REFCNT_MASK == 1 {
+				// Synchronize with other threads releases before deallocation
+				fence(Ordering::Acquire);
+			}
+			prev
+		};
+		#[cfg(not(feature = "sync-interner"))]
+		let prev = {
+			let prev = self.load();
+			self.utf8_refcnt.store(prev - 1, Ordering::Relaxed);
+			prev
+		};
+		(prev & REFCNT_MASK) - 1
+	}
+	/// Decrements refcnt, unless it is less than or equal to `min`
+	#[cfg(feature = "sync-interner")]
+	fn dec_refcnt_if_greater(&self, min: u32) -> bool {
+		let mut cur = self.load();
+		loop {
+			if cur & REFCNT_MASK <= min {
+				return false;
+			}
+			match self.utf8_refcnt.compare_exchange_weak(
+				cur,
+				cur - 1,
+				Ordering::Release,
+				Ordering::Relaxed,
+			) {
+				Ok(_) => return true,
+				Err(actual) => cur = actual,
+			}
+		}
 	}
 }
 
 /// Similar to Rc<[u8]>, but stores all data (refcnt, size) inline, instead of being DST
 pub struct Inner(NonNull<u8>);
+// SAFETY: with `sync-interner`, refcnt and flags are only modified atomically, and data is immutable
+#[cfg(feature = "sync-interner")]
+unsafe impl Send for Inner {}
+// SAFETY: same as Send
+#[cfg(feature = "sync-interner")]
+unsafe impl Sync for Inner {}
 impl Inner {
 	/// # Safety
 	/// `is_utf8` should only be set if data is really checked to be utf8
@@ -64,8 +148,11 @@ impl Inner {
 				mem::align_of::<InnerHeader>(),
 			));
 			assert!(!data.is_null());
-			*data.cast::<InnerHeader>() =
-				InnerHeader::new(bytes.len().try_into().expect("bytes > 4GB"), is_utf8);
+			*data.cast::<InnerHeader>() = InnerHeader::new(
+				bytes.len().try_into().expect("bytes > 4GB"),
+				is_utf8,
+				bytes.is_ascii(),
+			);
 			ptr::copy_nonoverlapping(
 				bytes.as_ptr(),
 				data.add(mem::size_of::<InnerHeader>()),
@@ -108,7 +195,7 @@ impl Inner {
 	///
 	/// Positive results are cached
 	pub fn check_utf8(this: &Self) -> bool {
-		let header = Self::header_mut(this);
+		let header = Self::header(this);
 		// SAFETY: header is initialized
 		if unsafe { (*header).is_utf8() } {
 			return true;
@@ -123,12 +210,19 @@ impl Inner {
 		}
 	}
 
+	/// Is data consisting only of ascii characters, computed once on creation
+	pub fn is_ascii(this: &Self) -> bool {
+		let header = Self::header(this);
+		// SAFETY: header is initialized
+		unsafe { (*header).is_ascii() }
+	}
+
 	/// Marks data as utf-8
 	///
 	/// # Safety
 	/// data should be really utf-8
 	pub unsafe fn assume_utf8(this: &Self) {
-		let header = Self::header_mut(this);
+		let header = Self::header(this);
 		// SAFETY: header is correct
 		unsafe { (*header).set_is_utf8() }
 	}
@@ -138,22 +232,26 @@ impl Inner {
 		#![allow(clippy::cast_ptr_alignment)]
 		this.0.as_ptr() as *const InnerHeader
 	}
-	const fn header_mut(this: &Self) -> *mut InnerHeader {
-		// in `new`, we allocate with correct alignment
-		#![allow(clippy::cast_ptr_alignment)]
-		this.0.as_ptr().cast::<InnerHeader>()
-	}
 
 	fn clone(this: &Self) -> Self {
-		let header = Self::header_mut(this);
+		let header = Self::header(this);
 		// SAFETY: header is initialized
-		unsafe {
-			let refcnt = (*header).refcnt() + 1;
-			(*header).set_refcnt(refcnt);
-		}
+		unsafe { (*header).inc_refcnt() };
 		Self(this.0)
 	}
 
+	/// Releases this reference, unless reference count is less than or equal to `min`,
+	/// returns true if reference was released
+	///
+	/// # Safety
+	/// On success, `this` should not be used or dropped anymore
+	#[cfg(feature = "sync-interner")]
+	pub unsafe fn release_if_greater(this: &Self, min: u32) -> bool {
+		let header = Self::header(this);
+		// SAFETY: header is initialized
+		unsafe { (*header).dec_refcnt_if_greater(min) }
+	}
+
 	pub fn ptr_eq(a: &Self, b: &Self) -> bool {
 		a.0 == b.0
 	}
@@ -162,7 +260,7 @@ impl Inner {
 		unsafe { this.0.as_ptr().add(mem::size_of::<InnerHeader>()) }
 	}
 
-	pub const fn strong_count(this: &Self) -> u32 {
+	pub fn strong_count(this: &Self) -> u32 {
 		let header = Self::header(this);
 		// SAFETY: header is initialized
 		unsafe { (*header).refcnt() }
@@ -180,7 +278,7 @@ impl Drop for Inner {
 		#[cold]
 		#[inline(never)]
 		fn dealloc(val: &Inner) {
-			let header = Inner::header_mut(val);
+			let header = Inner::header(val);
 			// SAFETY: size is correct, layout is valid
 			unsafe {
 				alloc::dealloc(
@@ -192,13 +290,9 @@ impl Drop for Inner {
 				);
 			}
 		}
-		let header = Self::header_mut(self);
+		let header = Self::header(self);
 		// SAFETY: header is initialized
-		let refcnt = unsafe {
-			let refcnt = (*header).refcnt() - 1;
-			(*header).set_refcnt(refcnt);
-			refcnt
-		};
+		let refcnt = unsafe { (*header).dec_refcnt() };
 		if refcnt == 0 {
 			dealloc(self);
 		}
@@ -232,3 +326,26 @@ impl Borrow<[u8]> for Inner {
 		self.as_slice()
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::panic;
+
+	use super::*;
+
+	#[test]
+	fn refcnt_overflow_keeps_flags() {
+		let header = InnerHeader::new(0, true, true);
+		header.utf8_refcnt.store(
+			UTF8_MASK | ASCII_MASK | (REFCNT_MASK - 1),
+			Ordering::Relaxed,
+		);
+		header.inc_refcnt();
+		assert_eq!(header.refcnt(), REFCNT_MASK);
+
+		assert!(panic::catch_unwind(|| header.inc_refcnt()).is_err());
+		assert_eq!(header.refcnt(), REFCNT_MASK);
+		assert!(header.is_utf8());
+		assert!(header.is_ascii());
+	}
+}
@@ -4,11 +4,19 @@
 	clippy::undocumented_unsafe_blocks
 )]
 #![warn(clippy::pedantic, clippy::nursery)]
+#[cfg(not(feature = "sync-interner"))]
+use std::cell::Cell;
+#[cfg(feature = "sync-interner")]
+use std::sync::{
+	atomic::{AtomicUsize, Ordering},
+	Mutex, PoisonError,
+};
 use std::{
 	borrow::Cow,
 	cell::RefCell,
 	fmt::{self, Display},
 	hash::{BuildHasherDefault, Hash, Hasher},
+	mem::ManuallyDrop,
 	ops::Deref,
 	str,
 };
@@ -24,7 +32,7 @@ use inner::Inner;
 ///
 /// Provides O(1) comparsions and hashing, cheap copy, and cheap conversion to [`IBytes`]
 #[derive(Clone, PartialOrd, Ord, Eq)]
-pub struct IStr(Inner);
+pub struct IStr(ManuallyDrop<Inner>);
 impl Trace for IStr {
 	fn is_type_tracked() -> bool {
 		false
@@ -41,6 +49,48 @@ impl IStr {
 	pub fn cast_bytes(self) -> IBytes {
 		IBytes(self.0.clone())
 	}
+
+	/// Returns char at `index`, O(1) for ASCII strings, as their ASCII-ness is known since interning
+	#[must_use]
+	pub fn char_at(&self, index: usize) -> Option<char> {
+		if Inner::is_ascii(&self.0) {
+			self.as_bytes().get(index).map(|b| char::from(*b))
+		} else {
+			self.chars().nth(index)
+		}
+	}
+
+	/// Interns concatenation of this string and `other`, see [`Self::concat_many`]
+	#[must_use]
+	pub fn concat(&self, other: &str) -> Self {
+		Self::concat_many(&[self.as_str(), other])
+	}
+
+	/// Interns concatenation of `parts`
+	///
+	/// Parts are joined in a reused thread-local buffer, so no allocation happens
+	/// if the result is already interned
+	#[must_use]
+	pub fn concat_many(parts: &[&str]) -> Self {
+		CONCAT_BUF.with(|buf| {
+			let mut buf = buf.borrow_mut();
+			buf.clear();
+			for part in parts {
+				buf.push_str(part);
+			}
+			intern_str(&buf)
+		})
+	}
+
+	/// Returns length of string in chars, O(1) for ASCII strings
+	#[must_use]
+	pub fn char_len(&self) -> usize {
+		if Inner::is_ascii(&self.0) {
+			self.len()
+		} else {
+			self.chars().count()
+		}
+	}
 }
 
 impl Deref for IStr {
@@ -74,19 +124,8 @@ impl Hash for IStr {
 
 impl Drop for IStr {
 	fn drop(&mut self) {
-		#[cold]
-		#[inline(never)]
-		fn unpool(inner: &Inner) {
-			// May fail on program termination
-			let res = POOL.try_with(|pool| pool.borrow_mut().remove(inner));
-			if res.is_ok() {
-				debug_assert_eq!(Inner::strong_count(inner), 1);
-			}
-		}
-		// First reference - current object, second - POOL
-		if Inner::strong_count(&self.0) <= 2 {
-			unpool(&self.0);
-		}
+		// SAFETY: self.0 is not used after
+		unsafe { release(&mut self.0) }
 	}
 }
 
@@ -104,7 +143,7 @@ impl Display for IStr {
 
 /// Interned byte array
 #[derive(Clone, PartialOrd, Ord, Eq)]
-pub struct IBytes(Inner);
+pub struct IBytes(ManuallyDrop<Inner>);
 impl Trace for IBytes {
 	fn is_type_tracked() -> bool {
 		false
@@ -158,19 +197,8 @@ impl Hash for IBytes {
 
 impl Drop for IBytes {
 	fn drop(&mut self) {
-		#[cold]
-		#[inline(never)]
-		fn unpool(inner: &Inner) {
-			// May fail on program termination
-			let res = POOL.try_with(|pool| pool.borrow_mut().remove(inner));
-			if res.is_ok() {
-				debug_assert_eq!(Inner::strong_count(inner), 1);
-			}
-		}
-		// First reference - current object, second - POOL
-		if Inner::strong_count(&self.0) <= 2 {
-			unpool(&self.0);
-		}
+		// SAFETY: self.0 is not used after
+		unsafe { release(&mut self.0) }
 	}
 }
 
@@ -220,22 +248,174 @@ impl<'de> serde::Deserialize<'de> for IStr {
 	}
 }
 
+/// Capacity of the pool, unless overridden with [`set_pool_capacity`]
+pub const DEFAULT_POOL_CAPACITY: usize = 200;
+
+thread_local! {
+	/// Buffer for [`IStr::concat_many`]
+	static CONCAT_BUF: RefCell<String> = RefCell::new(String::new());
+}
+
+type Pool = HashMap<Inner, (), BuildHasherDefault<FxHasher>>;
+
+fn new_pool(capacity: usize) -> Pool {
+	Pool::with_capacity_and_hasher(capacity, BuildHasherDefault::default())
+}
+
+#[cfg(not(feature = "sync-interner"))]
 thread_local! {
-	static POOL: RefCell<HashMap<Inner, (), BuildHasherDefault<FxHasher>>> = RefCell::new(HashMap::with_capacity_and_hasher(200, BuildHasherDefault::default()));
+	static POOL_CAPACITY: Cell<usize> = Cell::new(DEFAULT_POOL_CAPACITY);
+	static POOL: RefCell<Pool> = RefCell::new(new_pool(POOL_CAPACITY.with(Cell::get)));
+}
+
+#[cfg(feature = "sync-interner")]
+static POOL_CAPACITY: AtomicUsize = AtomicUsize::new(DEFAULT_POOL_CAPACITY);
+#[cfg(feature = "sync-interner")]
+static POOL: Mutex<Option<Pool>> = Mutex::new(None);
+
+/// Returns `None` if pool is already destroyed, which may happen on thread termination
+#[cfg(not(feature = "sync-interner"))]
+fn try_with_pool<T>(f: impl FnOnce(&mut Pool) -> T) -> Option<T> {
+	POOL.try_with(|pool| f(&mut pool.borrow_mut())).ok()
+}
+/// Pool is locked while `f` is running, so it shouldn't drop any [`IStr`]/[`IBytes`]
+#[cfg(feature = "sync-interner")]
+#[allow(clippy::unnecessary_wraps)]
+fn try_with_pool<T>(f: impl FnOnce(&mut Pool) -> T) -> Option<T> {
+	// Pool is never left in inconsistent state, so poisoning may be ignored
+	let mut pool = POOL.lock().unwrap_or_else(PoisonError::into_inner);
+	let pool = pool.get_or_insert_with(|| new_pool(POOL_CAPACITY.load(Ordering::Relaxed)));
+	Some(f(pool))
+}
+fn with_pool<T>(f: impl FnOnce(&mut Pool) -> T) -> T {
+	try_with_pool(f).expect("pool is alive")
+}
+
+/// Drops reference to interned data, removing data from the pool if no other references are left
+///
+/// # Safety
+/// `inner` should not be used after this call
+#[cfg(not(feature = "sync-interner"))]
+unsafe fn release(inner: &mut ManuallyDrop<Inner>) {
+	#[cold]
+	#[inline(never)]
+	fn unpool(inner: &Inner) {
+		// May fail on program termination
+		let res = try_with_pool(|pool| pool.remove(inner));
+		if res.is_some() {
+			debug_assert_eq!(Inner::strong_count(inner), 1);
+		}
+	}
+	// First reference - current object, second - POOL
+	if Inner::strong_count(inner) <= 2 {
+		unpool(inner);
+	}
+	// SAFETY: inner is not used after
+	unsafe { ManuallyDrop::drop(inner) }
+}
+
+/// Drops reference to interned data, removing data from the pool if no other references are left
+///
+/// References are only released without locking the pool while there is at least one more
+/// reference besides the pool one, and new references may only be obtained from the locked pool,
+/// so the last one always observes and removes the pool entry
+///
+/// # Safety
+/// `inner` should not be used after this call
+#[cfg(feature = "sync-interner")]
+unsafe fn release(inner: &mut ManuallyDrop<Inner>) {
+	#[cold]
+	#[inline(never)]
+	fn unpool(inner: &Inner) -> bool {
+		with_pool(|pool| {
+			// SAFETY: on success, inner is not used after
+			if unsafe { Inner::release_if_greater(inner, 2) } {
+				return false;
+			}
+			// Only the current reference and the pool one are left
+			pool.remove(inner);
+			true
+		})
+	}
+	// SAFETY: on success, inner is not used after
+	if unsafe { Inner::release_if_greater(inner, 2) } || !unpool(inner) {
+		return;
+	}
+	// SAFETY: inner is not used after
+	unsafe { ManuallyDrop::drop(inner) }
+}
+
+/// Sets initial capacity of the string pool
+///
+/// Pool is created lazily on first interning, so this function should be called
+/// before any [`IStr`]/[`IBytes`] is created, otherwise it has no effect.
+/// Without `sync-interner` feature, every thread has its own pool, and this function
+/// only affects pool of the current thread.
+///
+/// Useful for embedders which know they will intern lots of unique strings,
+/// as it avoids repeated pool rehashing on startup, see `pool_capacity` benchmark
+pub fn set_pool_capacity(capacity: usize) {
+	#[cfg(not(feature = "sync-interner"))]
+	POOL_CAPACITY.with(|c| c.set(capacity));
+	#[cfg(feature = "sync-interner")]
+	POOL_CAPACITY.store(capacity, Ordering::Relaxed);
+}
+
+/// Statistics of the string pool, returned by [`pool_stats`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolStats {
+	/// Number of unique strings and byte arrays currently interned
+	pub entries: usize,
+	/// Total length of interned data in bytes, not including per-entry headers and pool overhead
+	pub total_bytes: usize,
+}
+
+/// Returns statistics of the string pool
+///
+/// Without `sync-interner` feature, pool is thread-local, so only strings interned on the
+/// calling thread are counted, [`IStr`]/[`IBytes`] created on other threads live in their own pools.
+///
+/// Entries are removed from the pool once the last reference to them is dropped,
+/// so steady growth of returned values indicates interned strings being retained somewhere
+#[must_use]
+pub fn pool_stats() -> PoolStats {
+	with_pool(|pool| PoolStats {
+		entries: pool.len(),
+		total_bytes: pool.keys().map(|k| k.as_slice().len()).sum(),
+	})
+}
+
+/// Releases spare capacity of the string pool
+///
+/// Pool keeps its capacity after strings are removed from it, so after evaluating many
+/// short-lived documents it may stay much larger than needed. This function lets
+/// embedders reclaim that memory between jobs, without tearing down the whole thread.
+///
+/// Only spare capacity is affected: interned entries are kept, and every live [`IStr`]/[`IBytes`]
+/// stays valid and equal to newly interned copies of the same data.
+/// Without `sync-interner` feature, only pool of the current thread is shrunk.
+pub fn shrink_pool() {
+	with_pool(Pool::shrink_to_fit);
+}
+
+/// Returns number of entries in the string pool, see [`pool_stats`] for release builds
+#[cfg(debug_assertions)]
+#[must_use]
+pub fn pool_len() -> usize {
+	with_pool(|pool| pool.len())
 }
 
 #[must_use]
 pub fn intern_bytes(bytes: &[u8]) -> IBytes {
-	POOL.with(|pool| {
-		let mut pool = pool.borrow_mut();
+	with_pool(|pool| {
 		let entry = pool.raw_entry_mut().from_key(bytes);
 		match entry {
 			hashbrown::hash_map::RawEntryMut::Occupied(mut i) => {
-				IBytes(i.get_key_value().0.clone())
+				IBytes(ManuallyDrop::new(i.get_key_value().0.clone()))
 			}
 			hashbrown::hash_map::RawEntryMut::Vacant(e) => {
 				let (k, _) = e.insert(Inner::new_bytes(bytes), ());
-				IBytes(k.clone())
+				IBytes(ManuallyDrop::new(k.clone()))
 			}
 		}
 	})
@@ -246,3 +426,25 @@ pub fn intern_str(str: &str) -> IStr {
 	// SAFETY: Rust strings always utf8
 	unsafe { intern_bytes(str.as_bytes()).cast_str_unchecked() }
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn shrink_pool_keeps_entries() {
+		let kept = intern_str("shrink_pool_kept");
+		let dropped = (0..1000)
+			.map(|i| intern_str(&format!("shrink_pool_{i}")))
+			.collect::<Vec<_>>();
+		#[cfg(all(debug_assertions, not(feature = "sync-interner")))]
+		let len = pool_len();
+		drop(dropped);
+		shrink_pool();
+
+		#[cfg(all(debug_assertions, not(feature = "sync-interner")))]
+		assert_eq!(pool_len(), len - 1000);
+		assert_eq!(kept, intern_str("shrink_pool_kept"));
+		assert_eq!(kept.as_str(), "shrink_pool_kept");
+	}
+}
@@ -0,0 +1,67 @@
+//! Run with `cargo test -p jrsonnet-interner --features sync-interner`
+#![cfg(feature = "sync-interner")]
+
+use std::{
+	sync::{mpsc, Barrier},
+	thread,
+};
+
+use jrsonnet_interner::{intern_str, pool_stats, IStr};
+
+const THREADS: usize = 8;
+const ROUNDS: usize = 5000;
+const KEYS: usize = 64;
+
+// Single test in this binary, so no other test changes the global pool concurrently
+#[test]
+fn intern_clone_drop_across_threads() {
+	let shared = (0..KEYS)
+		.map(|i| intern_str(&format!("stress_shared_{i}")))
+		.collect::<Vec<_>>();
+	let baseline = pool_stats().entries;
+	let barrier = Barrier::new(THREADS);
+	let (tx, rx) = mpsc::channel::<IStr>();
+
+	thread::scope(|scope| {
+		for t in 0..THREADS {
+			let (shared, barrier, tx) = (&shared, &barrier, tx.clone());
+			scope.spawn(move || {
+				barrier.wait();
+				for r in 0..ROUNDS {
+					// Same keys are interned and released concurrently by every thread
+					let key = format!("stress_{}", (r + t) % KEYS);
+					let a = intern_str(&key);
+					let b = a.clone();
+					assert_eq!(a, intern_str(&key));
+					assert_eq!(b.as_str(), key);
+					drop(a);
+
+					let s = shared[(r * 7 + t) % KEYS].clone();
+					assert_eq!(
+						s,
+						intern_str(&format!("stress_shared_{}", (r * 7 + t) % KEYS))
+					);
+					// Dropped on the receiving thread
+					if r % 3 == 0 {
+						tx.send(b).unwrap();
+						tx.send(s).unwrap();
+					}
+				}
+			});
+		}
+		drop(tx);
+		// Released concurrently with interning on the sending threads
+		for v in rx {
+			let clone = v.clone();
+			drop(v);
+			assert!(clone.as_str().starts_with("stress_"));
+		}
+	});
+
+	assert_eq!(pool_stats().entries, baseline);
+	for (i, s) in shared.iter().enumerate() {
+		assert_eq!(s.as_str(), format!("stress_shared_{i}"));
+	}
+	drop(shared);
+	assert_eq!(pool_stats().entries, baseline - KEYS);
+}
@@ -300,7 +300,7 @@ fn builtin_inner(attr: BuiltinAttrs, fun: ItemFn) -> syn::Result<TokenStream> {
 					Some(value.clone())
 				} else {
 					None
-				}}
+				},}
 			} else {
 				quote! {
 					parsed.get(#name).expect("args shape is correct").clone(),
@@ -519,18 +519,28 @@ impl TypedField {
 		};
 
 		let name = self.name().unwrap();
+		let parse = quote! {
+			push_type_description(
+				s.clone(),
+				|| format!("property {}", #name),
+				|| ValuePathItem::Field(#name.into()),
+				|| <#ty>::from_untyped(value, s.clone()),
+			)?
+		};
 		let value = if self.is_option {
 			quote! {
 				if let Some(value) = obj.get(s.clone(), #name.into())? {
-					Some(<#ty>::from_untyped(value, s.clone())?)
+					Some(#parse)
 				} else {
 					None
 				}
 			}
 		} else {
-			quote! {
-				<#ty>::from_untyped(obj.get(s.clone(), #name.into())?.ok_or_else(|| Error::NoSuchField(#name.into(), vec![]))?, s.clone())?
-			}
+			quote! {{
+				let value = obj.get(s.clone(), #name.into())?
+					.ok_or_else(|| TypeError::MissingProperty(#name.into(), Self::TYPE.clone()))?;
+				#parse
+			}}
 		};
 
 		quote! {
@@ -603,8 +613,10 @@ fn derive_typed_inner(input: DeriveInput) -> Result<TokenStream> {
 				const TYPE: &'static ComplexValType = &ComplexValType::ObjectRef(&ITEMS);
 
 				fn from_untyped(value: Val, s: State) -> Result<Self> {
-					let obj = value.as_obj().expect("shape is correct");
-					Self::parse(&obj, s)
+					match value {
+						Val::Obj(obj) => Self::parse(&obj, s),
+						v => Err(TypeError::ExpectedGot(Self::TYPE.clone(), v.value_type()).into()),
+					}
 				}
 
 				fn into_untyped(value: Self, s: State) -> Result<Val> {
@@ -626,9 +638,9 @@ fn derive_typed_inner(input: DeriveInput) -> Result<TokenStream> {
 	Ok(quote! {
 		const _: () = {
 			use ::jrsonnet_evaluator::{
-				typed::{ComplexValType, Typed, TypedObj, CheckType},
+				typed::{ComplexValType, Typed, TypedObj, TypeError, ValuePathItem, push_type_description},
 				Val, State,
-				error::{LocError, Error, Result},
+				error::{LocError, Result},
 				ObjValueBuilder, ObjValue,
 			};
 
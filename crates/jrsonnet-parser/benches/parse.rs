@@ -0,0 +1,37 @@
+use std::borrow::Cow;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use jrsonnet_parser::{parse, ParserSettings, Source};
+
+/// Array of objects, all sharing the same field names
+fn repeated_fields(count: usize) -> String {
+	let mut out = String::from("[\n");
+	for i in 0..count {
+		out.push_str(&format!(
+			"  {{ name: 'item{i}', kind: 'a', value: {i}, enabled: true, labels: {{ app: 'x', tier: 'y' }} }},\n"
+		));
+	}
+	out.push(']');
+	out
+}
+
+/// Parsing of stdlib and identifier-heavy sources
+fn parse_sources(c: &mut Criterion) {
+	let mut group = c.benchmark_group("parse");
+	let settings = ParserSettings {
+		file_name: Source::new_virtual(Cow::Borrowed("<bench>")),
+	};
+	let mut inputs = vec![("stdlib".to_owned(), jrsonnet_stdlib::STDLIB_STR.to_owned())];
+	for count in [1_000, 10_000] {
+		inputs.push((format!("repeated_fields/{count}"), repeated_fields(count)));
+	}
+	for (name, code) in &inputs {
+		group.bench_with_input(BenchmarkId::from_parameter(name), code, |b, code| {
+			b.iter(|| parse(code, &settings).expect("bench input parses"));
+		});
+	}
+	group.finish();
+}
+
+criterion_group!(benches, parse_sources);
+criterion_main!(benches);
@@ -12,7 +12,7 @@ use serde::{Deserialize, Serialize};
 use crate::source::Source;
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, PartialEq, Trace)]
+#[derive(Clone, Debug, PartialEq, Trace)]
 pub enum FieldName {
 	/// {fixed: 2}
 	Fixed(IStr),
@@ -42,7 +42,7 @@ impl Visibility {
 pub struct AssertStmt(pub LocExpr, pub Option<LocExpr>);
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, PartialEq, Trace)]
+#[derive(Clone, Debug, PartialEq, Trace)]
 pub struct FieldMember {
 	pub name: FieldName,
 	pub plus: bool,
@@ -52,7 +52,7 @@ pub struct FieldMember {
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, PartialEq, Trace)]
+#[derive(Clone, Debug, PartialEq, Trace)]
 pub enum Member {
 	Field(FieldMember),
 	BindStmt(BindSpec),
@@ -356,6 +356,171 @@ pub enum Expr {
 	Slice(LocExpr, SliceDesc),
 }
 
+#[cfg_attr(
+	not(feature = "exp-destruct"),
+	allow(unused_variables, clippy::ptr_arg)
+)]
+fn destruct_children<'e>(destruct: &'e Destruct, out: &mut Vec<&'e LocExpr>) {
+	match destruct {
+		Destruct::Full(_) => {}
+		#[cfg(feature = "exp-destruct")]
+		Destruct::Skip => {}
+		#[cfg(feature = "exp-destruct")]
+		Destruct::Array { start, end, .. } => {
+			for d in start.iter().chain(end.iter()) {
+				destruct_children(d, out);
+			}
+		}
+		#[cfg(feature = "exp-destruct")]
+		Destruct::Object { fields, .. } => {
+			for (_, into, default) in fields {
+				if let Some(into) = into {
+					destruct_children(into, out);
+				}
+				out.extend(default);
+			}
+		}
+	}
+}
+fn params_children<'e>(params: &'e ParamsDesc, out: &mut Vec<&'e LocExpr>) {
+	for Param(destruct, default) in params.iter() {
+		destruct_children(destruct, out);
+		out.extend(default);
+	}
+}
+fn bind_children<'e>(bind: &'e BindSpec, out: &mut Vec<&'e LocExpr>) {
+	match bind {
+		BindSpec::Field { into, value } => {
+			destruct_children(into, out);
+			out.push(value);
+		}
+		BindSpec::Function { params, value, .. } => {
+			params_children(params, out);
+			out.push(value);
+		}
+	}
+}
+fn comp_children<'e>(specs: &'e [CompSpec], out: &mut Vec<&'e LocExpr>) {
+	for spec in specs {
+		match spec {
+			CompSpec::IfSpec(IfSpecData(cond)) => out.push(cond),
+			CompSpec::ForSpec(ForSpecData(_, over)) => out.push(over),
+		}
+	}
+}
+fn obj_children<'e>(body: &'e ObjBody, out: &mut Vec<&'e LocExpr>) {
+	match body {
+		ObjBody::MemberList(members) => {
+			for member in members {
+				match member {
+					Member::Field(FieldMember {
+						name,
+						params,
+						value,
+						..
+					}) => {
+						if let FieldName::Dyn(name) = name {
+							out.push(name);
+						}
+						if let Some(params) = params {
+							params_children(params, out);
+						}
+						out.push(value);
+					}
+					Member::BindStmt(bind) => bind_children(bind, out),
+					Member::AssertStmt(AssertStmt(cond, msg)) => {
+						out.push(cond);
+						out.extend(msg);
+					}
+				}
+			}
+		}
+		ObjBody::ObjComp(comp) => {
+			for bind in &comp.pre_locals {
+				bind_children(bind, out);
+			}
+			out.push(&comp.key);
+			out.push(&comp.value);
+			for bind in &comp.post_locals {
+				bind_children(bind, out);
+			}
+			comp_children(&comp.compspecs, out);
+		}
+	}
+}
+
+impl Expr {
+	/// Direct child expressions, in order of their appearance in source code
+	pub fn children(&self) -> Vec<&LocExpr> {
+		let mut out = Vec::new();
+		match self {
+			Self::Literal(_)
+			| Self::Str(_)
+			| Self::Num(_)
+			| Self::Var(_)
+			| Self::Import(_)
+			| Self::ImportStr(_)
+			| Self::ImportBin(_)
+			| Self::IntrinsicThisFile
+			| Self::IntrinsicId
+			| Self::Intrinsic(_) => {}
+			Self::Arr(items) => out.extend(items),
+			Self::ArrComp(value, specs) => {
+				out.push(value);
+				comp_children(specs, &mut out);
+			}
+			Self::Obj(body) => obj_children(body, &mut out),
+			Self::ObjExtend(base, body) => {
+				out.push(base);
+				obj_children(body, &mut out);
+			}
+			Self::Parened(value) | Self::UnaryOp(_, value) | Self::ErrorStmt(value) => {
+				out.push(value);
+			}
+			Self::BinaryOp(a, _, b) | Self::Index(a, b) => {
+				out.push(a);
+				out.push(b);
+			}
+			Self::AssertExpr(AssertStmt(cond, msg), value) => {
+				out.push(cond);
+				out.extend(msg);
+				out.push(value);
+			}
+			Self::LocalExpr(binds, value) => {
+				for bind in binds {
+					bind_children(bind, &mut out);
+				}
+				out.push(value);
+			}
+			Self::Apply(value, args, _) => {
+				out.push(value);
+				out.extend(&args.unnamed);
+				out.extend(args.named.iter().map(|(_, arg)| arg));
+			}
+			Self::Function(params, value) => {
+				params_children(params, &mut out);
+				out.push(value);
+			}
+			Self::IfElse {
+				cond: IfSpecData(cond),
+				cond_then,
+				cond_else,
+			} => {
+				out.push(cond);
+				out.push(cond_then);
+				out.extend(cond_else);
+			}
+			Self::Slice(value, desc) => {
+				out.push(value);
+				out.extend(&desc.start);
+				out.extend(&desc.end);
+				out.extend(&desc.step);
+			}
+		}
+		out
+	}
+}
+
 /// file, begin offset, end offset
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Clone, PartialEq, Eq, Trace)]
@@ -385,6 +550,16 @@ pub struct LocExpr(pub Rc<Expr>, pub ExprLocation);
 #[cfg(target_pointer_width = "64")]
 static_assertions::assert_eq_size!(LocExpr, [u8; 24]);
 
+impl LocExpr {
+	/// Calls `f` for this expression and all of its descendants, parents are visited before children
+	pub fn walk(&self, f: &mut impl FnMut(&LocExpr)) {
+		f(self);
+		for child in self.0.children() {
+			child.walk(f);
+		}
+	}
+}
+
 impl Debug for LocExpr {
 	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		if f.alternate() {
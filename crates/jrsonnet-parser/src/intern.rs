@@ -0,0 +1,23 @@
+use std::{borrow::Cow, cell::RefCell};
+
+use jrsonnet_interner::IStr;
+use rustc_hash::FxHashMap;
+
+/// Identifiers and field names, interned during single parse.
+///
+/// The same names are usually repeated many times in a file, and lookup in this cache is cheaper
+/// than lookup in the global pool, which still stays the only source of interned strings.
+/// Cache uses the same hasher as the pool, see `parse` benchmark
+#[derive(Default)]
+pub struct InternCache<'i>(RefCell<FxHashMap<Cow<'i, str>, IStr>>);
+
+impl<'i> InternCache<'i> {
+	pub fn intern(&self, str: Cow<'i, str>) -> IStr {
+		if let Some(interned) = self.0.borrow().get(&*str) {
+			return interned.clone();
+		}
+		let interned = IStr::from(&*str);
+		self.0.borrow_mut().insert(str, interned.clone());
+		interned
+	}
+}
@@ -1,12 +1,14 @@
 #![allow(clippy::redundant_closure_call, clippy::derive_partial_eq_without_eq)]
 
-use std::rc::Rc;
+use std::{borrow::Cow, rc::Rc};
 
 use peg::parser;
 mod expr;
 pub use expr::*;
 pub use jrsonnet_interner::IStr;
 pub use peg;
+mod intern;
+use intern::InternCache;
 mod source;
 mod unescape;
 pub use source::Source;
@@ -27,7 +29,7 @@ macro_rules! expr_un {
 }
 
 parser! {
-	grammar jsonnet_parser() for str {
+	grammar jsonnet_parser(names: &InternCache<'input>) for str {
 		use peg::ParseLiteral;
 
 		rule eof() = quiet!{![_]} / expected!("<eof>")
@@ -54,7 +56,7 @@ parser! {
 
 		/// Reserved word followed by any non-alphanumberic
 		rule reserved() = ("assert" / "else" / "error" / "false" / "for" / "function" / "if" / "import" / "importstr" / "importbin" / "in" / "local" / "null" / "tailstrict" / "then" / "self" / "super" / "true") end_of_ident()
-		rule id() -> IStr = v:$(quiet!{ !reserved() alpha() (alpha() / digit())*} / expected!("<identifier>")) { v.into() }
+		rule id() -> IStr = v:$(quiet!{ !reserved() alpha() (alpha() / digit())*} / expected!("<identifier>")) { names.intern(Cow::Borrowed(v)) }
 
 		rule keyword(id: &'static str) -> ()
 			= ##parse_string_literal(id) end_of_ident()
@@ -165,7 +167,7 @@ parser! {
 
 		pub rule field_name(s: &ParserSettings) -> expr::FieldName
 			= name:id() {expr::FieldName::Fixed(name)}
-			/ name:string() {expr::FieldName::Fixed(name.into())}
+			/ name:string() {expr::FieldName::Fixed(names.intern(Cow::Owned(name)))}
 			/ "[" _ expr:expr(s) _ "]" {expr::FieldName::Dyn(expr)}
 		pub rule visibility() -> expr::Visibility
 			= ":::" {expr::Visibility::Unhide}
@@ -349,7 +351,7 @@ parser! {
 
 pub type ParseError = peg::error::ParseError<peg::str::LineCol>;
 pub fn parse(str: &str, settings: &ParserSettings) -> Result<LocExpr, ParseError> {
-	jsonnet_parser::jsonnet(str, settings)
+	jsonnet_parser::jsonnet(str, &InternCache::default(), settings)
 }
 /// Used for importstr values
 pub fn string_to_expr(str: IStr, settings: &ParserSettings) -> LocExpr {
@@ -410,6 +412,44 @@ pub mod tests {
 		);
 	}
 
+	#[test]
+	fn walk_imports() {
+		let mut imports = Vec::new();
+		parse!(
+			"local a = import 'a.libsonnet'; { b: [importstr 'b.txt'], [a.c]: import 'c.jsonnet' }"
+		)
+		.walk(&mut |expr| match &*expr.0 {
+			Expr::Import(path) | Expr::ImportStr(path) | Expr::ImportBin(path) => {
+				imports.push(path.to_string());
+			}
+			_ => {}
+		});
+		assert_eq!(imports, ["a.libsonnet", "b.txt", "c.jsonnet"]);
+	}
+
+	#[test]
+	fn walk_field_names() {
+		let mut names = Vec::new();
+		parse!("{ a: { b: 1 }, c+: [{ d:: 2 }], local e = { f: 3 }, g(x): x { h: 4 } }").walk(
+			&mut |expr| {
+				if let Expr::Obj(ObjBody::MemberList(members))
+				| Expr::ObjExtend(_, ObjBody::MemberList(members)) = &*expr.0
+				{
+					for member in members {
+						if let Member::Field(FieldMember {
+							name: FieldName::Fixed(name),
+							..
+						}) = member
+						{
+							names.push(name.to_string());
+						}
+					}
+				}
+			},
+		);
+		assert_eq!(names, ["a", "c", "g", "b", "d", "f", "h"]);
+	}
+
 	#[test]
 	fn slice() {
 		parse!("a[1:]");